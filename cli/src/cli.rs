@@ -1,20 +1,54 @@
 use anyhow::{Result, anyhow};
+use chrono::{Local, Utc};
 use clap::{ArgAction, Parser, Subcommand};
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
 use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
 
-use crate::models::OpenPr;
-use crate::store::{StorePaths, load_settings, save_json};
+use crate::models::{AppSettings, LogLevel, OpenPr, ReportSummary, RunSnapshot, Verbosity};
+use crate::scaffold::write_example_scaffold;
+use crate::shell::{
+    disable_color, redact_github_token, set_github_token, set_max_stream_threads, sh_quote,
+};
+use crate::store::{StorePaths, load_last_pr_list, load_settings, save_json};
 use crate::workflow::{
-    print_pr_list, print_report, print_status, run_single_pr_by_number, run_workflow,
+    PrFilter, ProcessedFilter, ProgressSink, RUN_SETUP_FAILED_EXIT_CODE, RunOptions, StdoutSink,
+    WAIT_TIMEOUT_EXIT_CODE, export_workspace, ignore_pr, install_interrupt_handler, lint_templates,
+    parse_pr_list, ping, preview_commands, print_history, print_logs, print_pr_list, print_report,
+    print_run_status, print_stats, print_status, print_whoami, run_exit_code, run_pr_batch,
+    run_pr_list, run_single_pr_by_number, run_workflow, show_pr_report, skip_pr, unignore_pr,
+    unskip_pr, validate_settings_file, wait_for_run,
 };
 
 #[derive(Parser, Debug)]
 #[command(name = "pr-reviewer-cli")]
 #[command(about = "Interactive CLI for GitHub PR auto review/fix workflow")]
 pub struct Cli {
+    /// Workspace directory (overrides PR_REVIEWER_HOME and ~/.pr-reviewer-cli)
+    #[arg(long, global = true, value_name = "DIR")]
+    workspace: Option<PathBuf>,
+    /// Load settings from this file instead of the workspace's settings.json
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Save settings migrations back to the --config file
+    #[arg(long, global = true, requires = "config")]
+    config_writeback: bool,
+    /// Use this existing checkout instead of settings.repo_path (and settings.repos)
+    #[arg(long, global = true, value_name = "PATH")]
+    repo_path: Option<PathBuf>,
+    /// Stream raw review/fix command output
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+    /// Only print the final summary line
+    #[arg(long, short = 'q', global = true)]
+    quiet: bool,
+    /// Print without ANSI colors (also honored: the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    no_color: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -24,13 +58,51 @@ enum Commands {
     /// Start interactive shell (default)
     Shell,
     /// Execute a single workflow run and print progress
-    Run,
+    Run {
+        /// Only process PRs authored by this GitHub login
+        #[arg(long)]
+        author: Option<String>,
+        /// Only process this repository from settings.repos
+        #[arg(long)]
+        repo: Option<String>,
+        /// Skip resetting and pulling the default branch before the run
+        #[arg(long)]
+        no_sync: bool,
+        /// Stop at the first failed PR
+        #[arg(long)]
+        fail_fast: bool,
+        /// Only process PRs that are not marked as processed (default)
+        #[arg(long, conflicts_with = "include_processed")]
+        only_new: bool,
+        /// Also process PRs marked as processed, without clearing that list
+        #[arg(long)]
+        include_processed: bool,
+        /// Run exactly these PR numbers, e.g. `--prs 12,34`, like `run-pr`; listed PRs that are not open are skipped
+        #[arg(long, conflicts_with_all = ["author", "no_sync", "fail_fast", "only_new", "include_processed"])]
+        prs: Option<String>,
+    },
     /// List open PRs that can be reviewed
-    Prs,
+    Prs {
+        /// Only list PRs authored by this GitHub login
+        #[arg(long)]
+        author: Option<String>,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+        /// Print the list as a JSON array, each PR with a `state` of `new` or `processed`
+        #[arg(long)]
+        json: bool,
+    },
     /// Run review/fix for a specific PR number
     RunPr {
         #[arg(long)]
         pr: u64,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+        /// Narrow {{REVIEW_RANGE}} to the commits after this SHA
+        #[arg(long, value_name = "SHA")]
+        since_commit: Option<String>,
         #[arg(
             long,
             default_value_t = true,
@@ -39,23 +111,177 @@ enum Commands {
         )]
         compact: bool,
     },
+    /// Print the expanded review/fix commands and report path for a PR without running them
+    #[command(name = "preview-commands")]
+    Preview {
+        #[arg(long)]
+        pr: u64,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+        /// Narrow {{REVIEW_RANGE}} to the commits after this SHA
+        #[arg(long, value_name = "SHA")]
+        since_commit: Option<String>,
+    },
+    /// Run review/fix for every PR number listed in a file
+    RunBatch {
+        /// File with one PR number per line; blank lines and `#` comments are ignored
+        #[arg(long)]
+        pr_file: PathBuf,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+    /// Mark a PR as processed without running it
+    Skip {
+        #[arg(long)]
+        pr: u64,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+    /// Remove a PR from the processed list so it is picked up again
+    #[command(alias = "reset-pr")]
+    Unskip {
+        #[arg(long)]
+        pr: u64,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+    },
+    /// Never run or list a PR (adds it to ignored-prs.txt)
+    IgnorePr {
+        #[arg(long)]
+        pr: u64,
+    },
+    /// Remove a PR from ignored-prs.txt
+    UnignorePr {
+        #[arg(long)]
+        pr: u64,
+    },
     /// Show latest report summary and file
     Report,
+    /// Print the newest report file of one PR without re-running it
+    ShowReport {
+        #[arg(long)]
+        pr: u64,
+        /// Repository from settings.repos (default: the first one)
+        #[arg(long)]
+        repo: Option<String>,
+        /// List every kept report of the PR with its time instead
+        #[arg(long)]
+        all: bool,
+    },
     /// Show latest run status
-    Status,
+    Status {
+        /// Block until the current run finishes; exit 1 if it failed
+        #[arg(long)]
+        wait: bool,
+        /// Give up waiting after this many seconds (exit code 124)
+        #[arg(long, requires = "wait", value_name = "SECONDS")]
+        timeout: Option<u64>,
+    },
+    /// Check workspace, required tools and gh auth; exit non-zero on failure
+    Ping {
+        /// Also warn about review/fix templates missing expected placeholders
+        #[arg(long)]
+        template_check: bool,
+    },
+    /// Show the gh login and token scopes; exit non-zero if gh is not authenticated
+    #[command(name = "whoami")]
+    WhoAmI,
+    /// Show the all-time success rate and fixed PRs per calendar month
+    #[command(alias = "quota")]
+    Stats,
+    /// Print the log lines of the latest run, or of an archived run
+    Logs {
+        /// Archived run id (see `history`); default: the latest run
+        #[arg(long)]
+        run: Option<String>,
+        /// Only print the last N (matching) lines
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+        /// Only print lines matching this case-insensitive regex
+        #[arg(long, value_name = "PATTERN")]
+        grep: Option<String>,
+    },
+    /// List past runs, or show one archived run's report
+    History {
+        #[arg(long)]
+        run: Option<String>,
+    },
+    /// Write redacted settings, engine state and the latest run snapshot to one JSON file
+    Export {
+        /// File to write, e.g. pr-reviewer-export.json
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print settings, or edit them in $EDITOR
+    Settings {
+        #[command(subcommand)]
+        action: Option<SettingsAction>,
+    },
     /// Initialize default settings file if missing
-    Init,
+    Init {
+        /// Also write an annotated settings example, cron snippet and README stub
+        #[arg(long)]
+        example: bool,
+        /// Directory for the example files (default: the workspace root)
+        #[arg(long, requires = "example")]
+        output_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SettingsAction {
+    /// Open settings.json in $EDITOR and validate it on save
+    Edit,
+    /// Print one setting's current value, e.g. `settings get max_prs_per_run`
+    Get { key: String },
+}
+
+const PR_LIST_STALE_MINUTES: i64 = 10;
+const STATUS_WAIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Exits with `RUN_SETUP_FAILED_EXIT_CODE` when a run could not start at all.
+fn exit_on_setup_error(result: Result<RunSnapshot>) -> RunSnapshot {
+    result.unwrap_or_else(|err| {
+        eprintln!("Error: {err:?}");
+        std::process::exit(RUN_SETUP_FAILED_EXIT_CODE)
+    })
+}
+
+/// Exits non-zero when `snapshot` records a failed run; see `run_exit_code`.
+fn exit_with_run_status(snapshot: &RunSnapshot) -> Result<()> {
+    match run_exit_code(snapshot) {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
 }
 
 fn print_help() {
     println!("available commands:");
-    println!("  run       - execute workflow once and stream logs");
-    println!("  prs       - list all open PRs (with new/processed marker)");
+    println!("  run [--author LOGIN] [--repo NAME] - execute workflow once and stream logs");
+    println!("  prs [--author LOGIN] [--repo NAME] - list open PRs (with new/processed marker)");
     println!("  pick N [--no-compact]        - run review/fix for PR index from last `prs` list");
     println!("  run-pr X [--compact false]   - run review/fix for PR number X");
+    println!(
+        "  preview-commands X           - print PR X's expanded review/fix commands, run nothing"
+    );
+    println!("  skip X    - mark PR number X as processed without running it");
+    println!("  unskip X  - forget PR number X so it is picked up again (alias: reset-pr)");
+    println!("  ignore-pr X                  - never run or list PR X (ignored-prs.txt)");
+    println!("  unignore-pr X                - stop ignoring PR X");
     println!("  status    - show latest run status");
     println!("  report    - show latest run report and markdown");
+    println!("  show-report X [--all]        - print PR X's newest report, or list all of them");
+    println!("  stats     - show all-time success rate and fixed PRs per month (alias: quota)");
+    println!("  history [--run ID]           - list past runs or show an archived run");
+    println!("  logs [--run ID] [--tail N] [--grep PATTERN] - print a run's log lines");
+    println!("  whoami    - show the gh login and token scopes");
     println!("  settings  - print settings file path and content");
+    println!("  settings edit                - edit settings in $EDITOR and validate on save");
+    println!("  settings get KEY             - print one setting's current value");
     println!("  help      - show this help");
     println!("  quit/exit - leave shell");
 }
@@ -68,6 +294,33 @@ fn parse_bool_flag(value: &str) -> Option<bool> {
     }
 }
 
+/// `--run ID`, `--tail N` and `--grep PATTERN` of the shell `logs` command.
+fn parse_logs_args<'a>(
+    args: &[&'a str],
+) -> Result<(Option<&'a str>, Option<usize>, Option<&'a str>)> {
+    let (mut run_id, mut tail, mut grep) = (None, None, None);
+    let mut rest = args.iter();
+    while let Some(&token) = rest.next() {
+        let value = rest
+            .next()
+            .copied()
+            .ok_or_else(|| anyhow!("missing value for {token}"))?;
+        match token {
+            "--run" => run_id = Some(value),
+            "--tail" => {
+                tail = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| anyhow!("invalid --tail value: {value}"))?,
+                );
+            }
+            "--grep" => grep = Some(value),
+            _ => return Err(anyhow!("unknown option: {token}")),
+        }
+    }
+    Ok((run_id, tail, grep))
+}
+
 fn parse_compact_mode(args: &[&str]) -> Result<bool> {
     let mut compact = true;
     let mut index = 0usize;
@@ -101,7 +354,130 @@ fn parse_compact_mode(args: &[&str]) -> Result<bool> {
     Ok(compact)
 }
 
-fn run_shell_mode(paths: &StorePaths) -> Result<()> {
+fn parse_pr_filter(args: &[&str]) -> Result<PrFilter> {
+    let mut filter = PrFilter::default();
+    let mut index = 0usize;
+    while index < args.len() {
+        let token = args[index];
+        if token == "--author" {
+            let value = args
+                .get(index + 1)
+                .ok_or_else(|| anyhow!("--author requires a login"))?;
+            filter.author = Some((*value).to_string());
+            index += 2;
+            continue;
+        }
+        if let Some(value) = token.strip_prefix("--author=") {
+            filter.author = Some(value.to_string());
+            index += 1;
+            continue;
+        }
+        if token == "--repo" {
+            let value = args
+                .get(index + 1)
+                .ok_or_else(|| anyhow!("--repo requires a repository name"))?;
+            filter.repo = Some((*value).to_string());
+            index += 2;
+            continue;
+        }
+        if let Some(value) = token.strip_prefix("--repo=") {
+            filter.repo = Some(value.to_string());
+            index += 1;
+            continue;
+        }
+        return Err(anyhow!("unknown option: {token}"));
+    }
+    Ok(filter)
+}
+
+fn print_settings(paths: &StorePaths) {
+    println!("settings file: {}", paths.settings.display());
+    match fs::read_to_string(&paths.settings) {
+        Ok(content) => println!("{}", redact_github_token(&content)),
+        Err(err) => println!("read settings failed: {err}"),
+    }
+}
+
+/// Applies the process-wide settings (`github_token`, `max_stream_threads`) of
+/// an existing settings file; a missing or unreadable file is left for the
+/// command itself to report.
+fn apply_process_settings(paths: &StorePaths) {
+    if !paths.settings.exists() {
+        return;
+    }
+    if let Ok(settings) = load_settings(paths) {
+        set_max_stream_threads(settings.max_stream_threads);
+        set_github_token(settings.github_token);
+    }
+}
+
+/// Prints the current value of settings key `key`; unknown keys are an error.
+fn print_setting_value(paths: &StorePaths, key: &str) -> Result<()> {
+    let settings = load_settings(paths)?;
+    let value = settings.field_value(key).ok_or_else(|| {
+        anyhow!(
+            "unknown setting: {key} (known: {})",
+            AppSettings::field_names().join(", ")
+        )
+    })?;
+    println!("{}", redact_github_token(&value));
+    Ok(())
+}
+
+fn edit_settings(paths: &StorePaths) -> Result<()> {
+    if !paths.settings.exists() {
+        load_settings(paths)?;
+    }
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let status = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(format!(
+            "{editor} {}",
+            sh_quote(&paths.settings.display().to_string())
+        ))
+        .status()
+        .map_err(|e| anyhow!("failed to launch editor `{editor}`: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("editor `{editor}` exited with {status}"));
+    }
+
+    match validate_settings_file(paths) {
+        Ok(settings) => {
+            set_max_stream_threads(settings.max_stream_threads);
+            set_github_token(settings.github_token);
+            println!("settings saved and validated: {}", paths.settings.display());
+        }
+        Err(err) => {
+            println!("warning: settings file kept as edited but is invalid: {err:#}");
+            println!("run `settings edit` again to fix it");
+        }
+    }
+    Ok(())
+}
+
+/// Shell progress sink: prints like `StdoutSink` and asks push confirmations
+/// on the shell's line editor.
+struct ShellSink<'a> {
+    editor: &'a mut Editor<(), DefaultHistory>,
+}
+
+impl ProgressSink for ShellSink<'_> {
+    fn on_log(&mut self, level: LogLevel, line: &str) {
+        StdoutSink.on_log(level, line);
+    }
+
+    fn confirm_push(&mut self, pr: &OpenPr) -> bool {
+        let prompt = format!("push changes for PR #{}? [y/N] ", pr.number);
+        self.editor
+            .readline(&prompt)
+            .is_ok_and(|answer| parse_bool_flag(answer.trim()).unwrap_or(false))
+    }
+}
+
+fn run_shell_mode(paths: &StorePaths, verbosity: Verbosity) -> Result<()> {
     println!("PR Reviewer CLI Shell");
     println!("workspace: {}", paths.root.display());
     print_help();
@@ -111,7 +487,10 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
     let mut rl = Editor::<(), DefaultHistory>::new()
         .map_err(|e| anyhow!("failed to initialize line editor: {e}"))?;
     let _ = rl.load_history(&history_path);
-    let mut last_pr_list: Vec<OpenPr> = Vec::new();
+    let cached = load_last_pr_list(paths).unwrap_or_default();
+    let mut last_pr_list: Vec<OpenPr> = cached.prs;
+    let mut last_pr_list_at = cached.saved_at;
+    let mut last_pr_list_repo = cached.repo;
 
     loop {
         let input = match rl.readline(&prompt) {
@@ -132,24 +511,60 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
 
         let parts: Vec<&str> = command.split_whitespace().collect();
         match parts[0] {
-            "run" if parts.len() == 1 => match run_workflow(paths, true) {
-                Ok(snapshot) => {
-                    println!(
-                        "final status={:?}, progress={}/{}, error={}",
-                        snapshot.status,
-                        snapshot.current_index,
-                        snapshot.total_prs,
-                        snapshot.error_message.unwrap_or_else(|| "-".to_string())
-                    );
+            "run" => {
+                let filter = match parse_pr_filter(&parts[1..]) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        println!(
+                            "run options error: {err}. use `run [--author LOGIN] [--repo NAME]`"
+                        );
+                        continue;
+                    }
+                };
+                match run_workflow(
+                    paths,
+                    verbosity,
+                    &filter,
+                    RunOptions::default(),
+                    &mut ShellSink { editor: &mut rl },
+                ) {
+                    Ok(snapshot) => {
+                        println!(
+                            "final status={:?}, progress={}/{}, error={}",
+                            snapshot.status,
+                            snapshot.current_index,
+                            snapshot.total_prs,
+                            snapshot.error_message.unwrap_or_else(|| "-".to_string())
+                        );
+                        println!(
+                            "summary: {}",
+                            ReportSummary::from(snapshot.report.as_slice())
+                        );
+                    }
+                    Err(err) => {
+                        println!("run failed: {err}");
+                    }
                 }
-                Err(err) => {
-                    println!("run failed: {err}");
+            }
+            "prs" => {
+                let filter = match parse_pr_filter(&parts[1..]) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        println!(
+                            "prs options error: {err}. use `prs [--author LOGIN] [--repo NAME]`"
+                        );
+                        continue;
+                    }
+                };
+                match print_pr_list(paths, true, &filter, false) {
+                    Ok(prs) => {
+                        last_pr_list = prs;
+                        last_pr_list_at = Some(Utc::now());
+                        last_pr_list_repo = filter.repo;
+                    }
+                    Err(err) => println!("prs failed: {err}"),
                 }
-            },
-            "prs" if parts.len() == 1 => match print_pr_list(paths, true) {
-                Ok(prs) => last_pr_list = prs,
-                Err(err) => println!("prs failed: {err}"),
-            },
+            }
             "pick" if parts.len() >= 2 => {
                 let index = match parts[1].parse::<usize>() {
                     Ok(v) if v > 0 => v,
@@ -159,7 +574,7 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
                     }
                 };
                 let compact = match parse_compact_mode(&parts[2..]) {
-                    Ok(value) => value,
+                    Ok(value) => value && verbosity != Verbosity::Quiet,
                     Err(err) => {
                         println!(
                             "pick options error: {err}. use `pick N [--no-compact]` or `pick N --compact false`"
@@ -174,8 +589,25 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
                     );
                     continue;
                 }
+                if let Some(saved_at) = last_pr_list_at
+                    && Utc::now() - saved_at > chrono::Duration::minutes(PR_LIST_STALE_MINUTES)
+                {
+                    println!(
+                        "warning: PR list is from {} (over {} minutes old), run `prs` to refresh",
+                        saved_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                        PR_LIST_STALE_MINUTES
+                    );
+                }
                 let pr_number = last_pr_list[index - 1].number;
-                match run_single_pr_by_number(paths, pr_number, true, compact) {
+                match run_single_pr_by_number(
+                    paths,
+                    pr_number,
+                    last_pr_list_repo.as_deref(),
+                    None,
+                    verbosity,
+                    compact,
+                    &mut ShellSink { editor: &mut rl },
+                ) {
                     Ok(snapshot) => {
                         if !compact {
                             println!(
@@ -200,7 +632,7 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
                     }
                 };
                 let compact = match parse_compact_mode(&parts[2..]) {
-                    Ok(value) => value,
+                    Ok(value) => value && verbosity != Verbosity::Quiet,
                     Err(err) => {
                         println!(
                             "run-pr options error: {err}. use `run-pr X [--no-compact]` or `run-pr X --compact false`"
@@ -208,7 +640,15 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
                         continue;
                     }
                 };
-                match run_single_pr_by_number(paths, pr_number, true, compact) {
+                match run_single_pr_by_number(
+                    paths,
+                    pr_number,
+                    None,
+                    None,
+                    verbosity,
+                    compact,
+                    &mut ShellSink { editor: &mut rl },
+                ) {
                     Ok(snapshot) => {
                         if !compact {
                             println!(
@@ -224,6 +664,52 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
                     }
                 }
             }
+            "skip" | "unskip" | "reset-pr" if parts.len() == 2 => {
+                let pr_number = match parts[1].parse::<u64>() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("invalid pr number: {}", parts[1]);
+                        continue;
+                    }
+                };
+                let result = if parts[0] == "skip" {
+                    skip_pr(paths, None, pr_number)
+                } else {
+                    unskip_pr(paths, None, pr_number)
+                };
+                if let Err(err) = result {
+                    println!("{} failed: {err}", parts[0]);
+                }
+            }
+            "preview-commands" if parts.len() == 2 => {
+                let pr_number = match parts[1].parse::<u64>() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("invalid pr number: {}", parts[1]);
+                        continue;
+                    }
+                };
+                if let Err(err) = preview_commands(paths, pr_number, None, None) {
+                    println!("preview-commands failed: {err}");
+                }
+            }
+            "ignore-pr" | "unignore-pr" if parts.len() == 2 => {
+                let pr_number = match parts[1].parse::<u64>() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("invalid pr number: {}", parts[1]);
+                        continue;
+                    }
+                };
+                let result = if parts[0] == "ignore-pr" {
+                    ignore_pr(paths, pr_number)
+                } else {
+                    unignore_pr(paths, pr_number)
+                };
+                if let Err(err) = result {
+                    println!("{} failed: {err}", parts[0]);
+                }
+            }
             "status" => {
                 if let Err(err) = print_status(paths) {
                     println!("status failed: {err}");
@@ -234,11 +720,69 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
                     println!("report failed: {err}");
                 }
             }
-            "settings" => {
-                println!("settings file: {}", paths.settings.display());
-                match fs::read_to_string(&paths.settings) {
-                    Ok(content) => println!("{content}"),
-                    Err(err) => println!("read settings failed: {err}"),
+            "show-report" if matches!(parts.len(), 2 | 3) => {
+                let all = match parts.get(2) {
+                    None => false,
+                    Some(&"--all") => true,
+                    Some(_) => {
+                        println!("usage: show-report X [--all]");
+                        continue;
+                    }
+                };
+                let pr_number = match parts[1].parse::<u64>() {
+                    Ok(v) => v,
+                    Err(_) => {
+                        println!("invalid pr number: {}", parts[1]);
+                        continue;
+                    }
+                };
+                if let Err(err) = show_pr_report(paths, None, pr_number, all) {
+                    println!("show-report failed: {err}");
+                }
+            }
+            "whoami" => {
+                if let Err(err) = print_whoami() {
+                    println!("whoami failed: {err:#}");
+                }
+            }
+            "stats" | "quota" => {
+                if let Err(err) = print_stats(paths) {
+                    println!("stats failed: {err}");
+                }
+            }
+            "logs" => match parse_logs_args(&parts[1..]) {
+                Ok((run_id, tail, grep)) => {
+                    if let Err(err) = print_logs(paths, run_id, tail, grep) {
+                        println!("logs failed: {err}");
+                    }
+                }
+                Err(err) => {
+                    println!("{err}");
+                    println!("usage: logs [--run ID] [--tail N] [--grep PATTERN]");
+                }
+            },
+            "history" => {
+                let run_id = match parts[1..] {
+                    [] => None,
+                    ["--run", id] | [id] => Some(id),
+                    _ => {
+                        println!("usage: history [--run ID]");
+                        continue;
+                    }
+                };
+                if let Err(err) = print_history(paths, run_id) {
+                    println!("history failed: {err}");
+                }
+            }
+            "settings" if parts.len() == 1 => print_settings(paths),
+            "settings" if parts[1..] == ["edit"] => {
+                if let Err(err) = edit_settings(paths) {
+                    println!("settings edit failed: {err}");
+                }
+            }
+            "settings" if parts.len() == 3 && parts[1] == "get" => {
+                if let Err(err) = print_setting_value(paths, parts[2]) {
+                    println!("settings get failed: {err}");
                 }
             }
             "help" if parts.len() == 1 => print_help(),
@@ -256,43 +800,221 @@ fn run_shell_mode(paths: &StorePaths) -> Result<()> {
 
 pub fn run_app() -> Result<()> {
     let cli = Cli::parse();
-    let paths = StorePaths::new()?;
+    let mut paths = match &cli.workspace {
+        Some(dir) => StorePaths::with_root(dir)?,
+        None => StorePaths::new()?,
+    };
+    if let Some(config) = &cli.config {
+        paths.use_settings_file(config, cli.config_writeback)?;
+    }
+    if let Some(repo_path) = &cli.repo_path {
+        let repo_path = fs::canonicalize(repo_path)
+            .map_err(|e| anyhow!("--repo-path {}: {e}", repo_path.display()))?;
+        if !repo_path.is_dir() {
+            return Err(anyhow!(
+                "--repo-path {} is not a directory",
+                repo_path.display()
+            ));
+        }
+        paths.repo_path_override = Some(repo_path);
+    }
+    // Installed before any command runs: on Ctrl-C the live snapshot is marked
+    // failed ("interrupted by user"), the running child is killed, the repo is
+    // switched back to the default branch and the process exits with 130.
+    install_interrupt_handler(&paths)?;
+    let verbosity = Verbosity::from_flags(cli.verbose, cli.quiet);
+    if cli.no_color {
+        disable_color();
+    }
+    apply_process_settings(&paths);
 
     match cli.command.unwrap_or(Commands::Shell) {
-        Commands::Shell => run_shell_mode(&paths),
-        Commands::Run => {
-            let snapshot = run_workflow(&paths, true)?;
+        Commands::Shell => run_shell_mode(&paths, verbosity),
+        Commands::Run {
+            author,
+            repo,
+            no_sync,
+            fail_fast,
+            only_new: _,
+            include_processed,
+            prs,
+        } => {
+            if no_sync {
+                println!(
+                    "warning: --no-sync skips the repository sync, the default branch may be stale"
+                );
+            }
+            let snapshot = match prs {
+                Some(prs) => exit_on_setup_error(parse_pr_list(&prs).and_then(|numbers| {
+                    run_pr_list(&paths, numbers, repo.as_deref(), verbosity, &mut StdoutSink)
+                })),
+                None => exit_on_setup_error(run_workflow(
+                    &paths,
+                    verbosity,
+                    &PrFilter { author, repo },
+                    RunOptions {
+                        sync: !no_sync,
+                        fail_fast,
+                        processed: if include_processed {
+                            ProcessedFilter::IncludeProcessed
+                        } else {
+                            ProcessedFilter::OnlyNew
+                        },
+                    },
+                    &mut StdoutSink,
+                )),
+            };
             println!(
                 "final status={:?}, total_prs={}, done={}, error={}",
                 snapshot.status,
                 snapshot.total_prs,
                 snapshot.current_index,
-                snapshot.error_message.unwrap_or_else(|| "-".to_string())
+                snapshot.error_message.as_deref().unwrap_or("-")
             );
-            Ok(())
+            println!(
+                "summary: {}",
+                ReportSummary::from(snapshot.report.as_slice())
+            );
+            exit_with_run_status(&snapshot)
         }
-        Commands::Prs => {
-            let _ = print_pr_list(&paths, true)?;
+        Commands::Prs { author, repo, json } => {
+            let _ = print_pr_list(&paths, true, &PrFilter { author, repo }, json)?;
             Ok(())
         }
-        Commands::RunPr { pr, compact } => {
-            let snapshot = run_single_pr_by_number(&paths, pr, true, compact)?;
+        Commands::RunPr {
+            pr,
+            repo,
+            since_commit,
+            compact,
+        } => {
+            let compact = compact && verbosity != Verbosity::Quiet;
+            let snapshot = exit_on_setup_error(run_single_pr_by_number(
+                &paths,
+                pr,
+                repo.as_deref(),
+                since_commit.as_deref(),
+                verbosity,
+                compact,
+                &mut StdoutSink,
+            ));
             if !compact {
                 println!(
                     "selected PR done: status={:?}, pr=#{} error={}",
                     snapshot.status,
                     pr,
-                    snapshot.error_message.unwrap_or_else(|| "-".to_string())
+                    snapshot.error_message.as_deref().unwrap_or("-")
                 );
             }
+            exit_with_run_status(&snapshot)
+        }
+        Commands::Preview {
+            pr,
+            repo,
+            since_commit,
+        } => {
+            preview_commands(&paths, pr, repo.as_deref(), since_commit.as_deref())?;
             Ok(())
         }
+        Commands::RunBatch { pr_file, repo } => {
+            let snapshot = exit_on_setup_error(run_pr_batch(
+                &paths,
+                &pr_file,
+                repo.as_deref(),
+                verbosity,
+                &mut StdoutSink,
+            ));
+            println!(
+                "final status={:?}, total_prs={}, done={}, error={}",
+                snapshot.status,
+                snapshot.total_prs,
+                snapshot.current_index,
+                snapshot.error_message.as_deref().unwrap_or("-")
+            );
+            println!(
+                "summary: {}",
+                ReportSummary::from(snapshot.report.as_slice())
+            );
+            exit_with_run_status(&snapshot)
+        }
+        Commands::Skip { pr, repo } => skip_pr(&paths, repo.as_deref(), pr),
+        Commands::Unskip { pr, repo } => unskip_pr(&paths, repo.as_deref(), pr),
+        Commands::IgnorePr { pr } => ignore_pr(&paths, pr),
+        Commands::UnignorePr { pr } => unignore_pr(&paths, pr),
         Commands::Report => print_report(&paths),
-        Commands::Status => print_status(&paths),
-        Commands::Init => {
+        Commands::ShowReport { pr, repo, all } => show_pr_report(&paths, repo.as_deref(), pr, all),
+        Commands::Status { wait: false, .. } => print_status(&paths),
+        Commands::Status {
+            wait: true,
+            timeout,
+        } => match wait_for_run(
+            &paths,
+            timeout.map(Duration::from_secs),
+            STATUS_WAIT_POLL_INTERVAL,
+        )? {
+            Some(snapshot) => {
+                print_run_status(&snapshot);
+                exit_with_run_status(&snapshot)
+            }
+            None => {
+                println!("timed out waiting for the run to finish");
+                print_status(&paths)?;
+                std::process::exit(WAIT_TIMEOUT_EXIT_CODE);
+            }
+        },
+        Commands::Stats => print_stats(&paths),
+        Commands::Ping { template_check } => match ping(&paths) {
+            Ok(()) => {
+                if template_check {
+                    for warning in lint_templates(&load_settings(&paths)?) {
+                        println!("warning: {warning}");
+                    }
+                }
+                println!("ok");
+                Ok(())
+            }
+            Err((failure, err)) => {
+                println!("ping failed [{}]: {err:#}", failure.label());
+                std::process::exit(failure.exit_code());
+            }
+        },
+        Commands::WhoAmI => print_whoami(),
+        Commands::Export { out } => export_workspace(&paths, &out),
+        Commands::Logs { run, tail, grep } => {
+            print_logs(&paths, run.as_deref(), tail, grep.as_deref())
+        }
+        Commands::History { run } => print_history(&paths, run.as_deref()),
+        Commands::Settings { action: None } => {
+            print_settings(&paths);
+            Ok(())
+        }
+        Commands::Settings {
+            action: Some(SettingsAction::Edit),
+        } => edit_settings(&paths),
+        Commands::Settings {
+            action: Some(SettingsAction::Get { key }),
+        } => print_setting_value(&paths, &key),
+        Commands::Init {
+            example,
+            output_dir,
+        } => {
             let settings = load_settings(&paths)?;
-            save_json(&paths.settings, &settings)?;
+            if paths.settings_writeback {
+                save_json(&paths.settings, &settings)?;
+            }
             println!("settings initialized: {}", paths.settings.display());
+            if example {
+                let dir = output_dir.unwrap_or_else(|| paths.root.clone());
+                let executable = std::env::current_exe()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|_| "pr-reviewer-cli".to_string());
+                let written = write_example_scaffold(&paths, &dir, &executable)?;
+                if written.is_empty() {
+                    println!("example files already exist in {}", dir.display());
+                }
+                for path in written {
+                    println!("example written: {}", path.display());
+                }
+            }
             Ok(())
         }
     }
@@ -1,5 +1,6 @@
 pub mod cli;
 pub mod models;
+pub mod scaffold;
 pub mod shell;
 pub mod store;
 pub mod workflow;
@@ -0,0 +1,462 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::AppSettings;
+use crate::store::StorePaths;
+
+pub const SETTINGS_EXAMPLE_FILE: &str = "settings.example.jsonc";
+pub const CRON_EXAMPLE_FILE: &str = "cron.example";
+pub const README_STUB_FILE: &str = "README.md";
+
+/// One line of documentation per `AppSettings` field, in the order they are written out.
+const FIELD_DOCS: &[(&str, &str)] = &[
+    (
+        "repo_path",
+        "Local checkout used for reviews; cloned from repo_clone_url when empty.",
+    ),
+    (
+        "repo_clone_url",
+        "Clone URL used for the first-time clone of repo_path.",
+    ),
+    (
+        "default_branch",
+        "Base branch PRs are reviewed against ({{DEFAULT_BRANCH}}); empty or \"auto\" detects it from origin/HEAD.",
+    ),
+    (
+        "max_prs_per_run",
+        "Maximum number of PRs processed by one `run`.",
+    ),
+    (
+        "max_command_retries",
+        "Attempts for retryable git/gh/codex failures.",
+    ),
+    ("retry_delay_seconds", "Delay between retry attempts."),
+    (
+        "review_command_template",
+        "Review command run in the PR checkout; supports template placeholders.",
+    ),
+    (
+        "fix_command_template",
+        "Fix command run after the review; supports template placeholders.",
+    ),
+    (
+        "auto_push_enabled",
+        "Commit and push fixes back to the PR branch.",
+    ),
+    (
+        "reports_dir",
+        "Directory for markdown reports; null uses <workspace>/reports, relative paths resolve against the workspace.",
+    ),
+    (
+        "max_snapshot_history",
+        "Finished runs kept in snapshots/; 0 disables archiving.",
+    ),
+    (
+        "strip_co_authors",
+        "Remove matching Co-authored-by trailers from fix commits.",
+    ),
+    (
+        "co_author_strip_patterns",
+        "Case-insensitive substrings; empty strips every Co-authored-by trailer.",
+    ),
+    (
+        "rate_limit_min_remaining",
+        "Wait for the GitHub rate limit reset when fewer requests remain.",
+    ),
+    (
+        "rate_limit_max_wait_seconds",
+        "Longest rate limit wait before the run fails instead.",
+    ),
+    (
+        "post_fix_command_template",
+        "Optional command run after a successful fix, e.g. tests; null disables.",
+    ),
+    (
+        "post_fix_required",
+        "Skip the push when the post-fix command fails.",
+    ),
+    (
+        "clone_ignored_entries",
+        "Entries that do not count when checking repo_path is empty before cloning.",
+    ),
+    (
+        "reports_per_pr_limit",
+        "Markdown reports kept per PR; 0 keeps all.",
+    ),
+    (
+        "review_clean_marker",
+        "Review output containing this (and no findings) skips fix and push; \"\" always fixes.",
+    ),
+    (
+        "repo_overrides_enabled",
+        "Read .pr-reviewer.toml from the PR checkout to override templates and auto push.",
+    ),
+    (
+        "usage_pattern",
+        "Regex with named groups `tokens` and `cost` matched against review/fix stderr.",
+    ),
+    (
+        "command_allowlist",
+        "Programs the expanded review and fix commands (and their fallbacks) may start with; anything else is refused as a security error. The post-fix hook is not checked. [] allows everything.",
+    ),
+    (
+        "report_aggregation",
+        "\"per_run\" (default) keeps only per-PR report files; \"daily\" also collects every PR's report as a section of reports/daily-<YYYY-MM-DD>.md.",
+    ),
+    (
+        "sanitize_mode",
+        "How strip_co_authors cleans the bot's commit: \"amend\" (default) rewrites it unless it is already on the remote, \"skip\" never rewrites it.",
+    ),
+    (
+        "session_pattern",
+        "Regex (named group `session`, else the whole match) that finds a session id in the review output; the fix command gets it as {{CODEX_SESSION}}.",
+    ),
+    (
+        "repos",
+        "Optional list of {name, repo_path, repo_clone_url, default_branch}; when set, runs cover every entry instead of the top-level repo fields.",
+    ),
+    (
+        "report_format",
+        "Per-PR report file format: \"markdown\" (.md), \"json\" (.json) or \"text\" (.txt).",
+    ),
+    (
+        "idle_timeout_seconds",
+        "Kill review/fix commands that print nothing for this long (e.g. stuck on a prompt); 0 disables.",
+    ),
+    (
+        "retryable_exit_codes",
+        "Exit codes of review/fix commands that are retried; empty retries every non-zero exit except auth/not-found errors.",
+    ),
+    (
+        "pr_search_query",
+        "GitHub search query passed to `gh pr list --search` (e.g. \"review:required -label:wip\"); null lists all open PRs.",
+    ),
+    (
+        "fail_fast",
+        "Stop `run` at the first failed PR; the remaining PRs are left for the next run.",
+    ),
+    (
+        "command_fallbacks",
+        "Rules {match_stderr, match_command_substring, replacement_template}: a review/fix command failing with matching stderr is retried once with the replacement.",
+    ),
+    (
+        "apply_label_on_push",
+        "Label added to a PR after fixes were pushed to it (e.g. \"auto-fixed\"); null disables.",
+    ),
+    (
+        "remove_label_on_push",
+        "Label removed from a PR after fixes were pushed to it (e.g. \"needs-fix\"); null disables.",
+    ),
+    (
+        "pr_number_tiebreak",
+        "Order of PRs updated at the same time: \"descending\" (default, higher numbers first) or \"ascending\".",
+    ),
+    (
+        "pull_strategy",
+        "How the sync updates the default branch: \"ff-only\" (default) fails if it diverged, \"rebase\" replays local commits, \"reset-to-remote\" drops them with `git reset --hard <remote>/<branch>`.",
+    ),
+    (
+        "rollback_mode",
+        "How local changes in repo_path are cleared before syncing: \"reset\" discards them, \"stash\" keeps them in `git stash`.",
+    ),
+    (
+        "stash_max_age_days",
+        "In stash mode, drop pr-reviewer stashes older than this many days; 0 keeps them all.",
+    ),
+    (
+        "fix_prompt_prefix",
+        "Text inserted at the start of the quoted prompt in fix_command_template; null disables.",
+    ),
+    (
+        "fix_prompt_suffix",
+        "Text appended to the end of the quoted prompt in fix_command_template; null disables.",
+    ),
+    (
+        "fix_prompt_file",
+        "File (relative to the workspace) whose contents become the fix prompt of `codex exec \"...\"`, replacing fix_command_template; null disables.",
+    ),
+    (
+        "max_captured_output_bytes",
+        "Bytes of stdout and of stderr kept per review/fix command (head and tail; output still streams in full); 0 keeps everything.",
+    ),
+    (
+        "bot_git_name",
+        "user.name for the bot's fix commits; null uses the git config.",
+    ),
+    (
+        "bot_git_email",
+        "user.email for the bot's fix commits; null uses the git config.",
+    ),
+    (
+        "skip_title_patterns",
+        "PRs whose title contains one of these (case-insensitive) are skipped by `prs` and `run`; write /.../ for a regex.",
+    ),
+    (
+        "clone_depth",
+        "Shallow-clone repo_path with `--depth N` and fetch only the default branch at that depth; null clones full history.",
+    ),
+    (
+        "partial_clone",
+        "Clone with `--filter=blob:none` (blobs are fetched on demand) and fetch only origin.",
+    ),
+    (
+        "interactive_confirm_push",
+        "In the interactive shell, ask `push changes for PR #N? [y/N]` before each push; command-line runs never ask.",
+    ),
+    (
+        "command_wrapper_template",
+        "Run commands inside this template, e.g. `docker run --rm -v {{REPO_PATH}}:/repo -w /repo image sh -c {COMMAND}`; {COMMAND} becomes the quoted command. null runs on the host.",
+    ),
+    (
+        "command_wrapper_scope",
+        "Commands command_wrapper_template applies to: `codex` (review/fix/commit-message commands) or `all` (also git and gh in repo_path).",
+    ),
+    (
+        "include_diff_in_report",
+        "After the fix step, add the diff of its changes to the PR's review report (skipped when the fix changed nothing).",
+    ),
+    (
+        "max_diff_bytes",
+        "Bytes of fix diff kept in the report; longer diffs are cut at a line with a truncation note. 0 keeps all.",
+    ),
+    (
+        "max_pr_retries",
+        "Run a whole PR again (checkout, review, fix, push) up to N times when it failed in a command with a transient error; on top of max_command_retries.",
+    ),
+    (
+        "squash_before_push",
+        "Fold commits the fix step made itself into one `chore: auto-fix` commit before pushing; the PR author's commits are kept.",
+    ),
+    (
+        "max_changed_files",
+        "PRs changing more files than this are too large for a useful review; null disables the check.",
+    ),
+    (
+        "large_pr_action",
+        "What happens to a PR over max_changed_files: \"skip\" (default) reports it as skipped before checkout, \"warn\" only logs a warning.",
+    ),
+    (
+        "max_stream_threads",
+        "Cap on output reader threads across running commands, two per command; 0 means no cap, otherwise at least 2.",
+    ),
+    (
+        "cleanup_pr_branches",
+        "After a run, delete the local branches `gh pr checkout` created for its PRs; the default branch is never deleted.",
+    ),
+    (
+        "cleanup_git_gc",
+        "Run `git gc --auto` after cleanup_pr_branches deleted branches.",
+    ),
+    (
+        "skip_fix_if_unresolved_threads",
+        "Skip fix and push while a PR has unresolved review threads started by humans; the PR is retried on later runs.",
+    ),
+    (
+        "recheck_pr_state",
+        "Ask gh for each PR's state right before running it and skip PRs merged or closed since the list was loaded.",
+    ),
+    (
+        "remote_name",
+        "Remote the default branch is synced from and fixes are pushed to, e.g. \"upstream\" in a fork-based flow; must exist in repo_path.",
+    ),
+    (
+        "capture_review_stdout_as_report",
+        "With a review template that does not use {{REPORT_PATH}}, store the review's stdout as the report body itself instead of the usual report layout.",
+    ),
+    (
+        "max_run_duration_seconds",
+        "Stop starting new PRs once a `run` has taken this many seconds; the rest are reported as skipped and picked up next time. null means no limit.",
+    ),
+    (
+        "github_token",
+        "Token exported as GH_TOKEN to gh (and every other command) instead of relying on `gh auth login`; masked in logs, errors and settings_used.",
+    ),
+];
+
+const TEMPLATE_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{{PR_NUMBER}}", "PR number"),
+    ("{{PR_TITLE}}", "PR title"),
+    ("{{PR_URL}}", "PR web URL"),
+    ("{{PR_BRANCH}}", "PR head branch"),
+    ("{{DEFAULT_BRANCH}}", "`default_branch` setting"),
+    ("{{REPO_PATH}}", "`repo_path` setting"),
+    ("{{WORK_DIR}}", "directory the command runs in"),
+    ("{{REPORT_PATH}}", "markdown report for the PR"),
+    (
+        "{{REPORT_DIR}}",
+        "directory of the report; files left there are listed by `report`",
+    ),
+    (
+        "{{REVIEW_RANGE}}",
+        "commits to review, e.g. `<merge-base>..HEAD` (`<sha>..HEAD` with `run-pr --since-commit`)",
+    ),
+    (
+        "{{CODEX_SESSION}}",
+        "fix command only: review session id found by `session_pattern`, empty if none",
+    ),
+];
+
+/// Renders the default settings as JSON with a `//` comment above each field.
+pub fn render_settings_example() -> Result<String> {
+    let Value::Object(mut fields) = serde_json::to_value(AppSettings::default())? else {
+        unreachable!("AppSettings serializes to an object");
+    };
+    let mut entries = Vec::new();
+    for (key, doc) in FIELD_DOCS {
+        if let Some(value) = fields.remove(*key) {
+            entries.push((key.to_string(), *doc, value));
+        }
+    }
+    entries.extend(fields.into_iter().map(|(key, value)| (key, "", value)));
+
+    let mut out = String::from(
+        "// Annotated copy of the default settings.json. Copy the fields you need into\n\
+         // settings.json (plain JSON, without these comments).\n{\n",
+    );
+    let total = entries.len();
+    for (index, (key, doc, value)) in entries.into_iter().enumerate() {
+        if !doc.is_empty() {
+            out.push_str(&format!("  // {doc}\n"));
+        }
+        let separator = if index + 1 < total { "," } else { "" };
+        out.push_str(&format!(
+            "  {}: {}{separator}\n",
+            serde_json::to_string(&key)?,
+            serde_json::to_string(&value)?
+        ));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+pub fn render_cron_example(paths: &StorePaths, executable: &str) -> String {
+    format!(
+        "# Run the review workflow every 30 minutes (install with `crontab -e`).\n\
+         */30 * * * * PR_REVIEWER_HOME={root} {executable} --quiet run >> {logs}/cron.log 2>&1\n",
+        root = paths.root.display(),
+        logs = paths.logs.display(),
+    )
+}
+
+pub fn render_readme_stub(paths: &StorePaths) -> String {
+    let mut out = format!(
+        "# pr-reviewer-cli workspace\n\n\
+         Settings are read from `{}`. `{SETTINGS_EXAMPLE_FILE}` lists every field with its default value.\n\n\
+         ## Settings\n\n",
+        paths.settings.display()
+    );
+    for (key, doc) in FIELD_DOCS {
+        out.push_str(&format!("- `{key}`: {doc}\n"));
+    }
+    out.push_str("\n## Template placeholders\n\n");
+    for (placeholder, meaning) in TEMPLATE_PLACEHOLDERS {
+        out.push_str(&format!("- `{placeholder}`: {meaning}\n"));
+    }
+    out.push_str(&format!(
+        "\n## Scheduling\n\nSee `{CRON_EXAMPLE_FILE}` for a crontab entry running `run` periodically.\n"
+    ));
+    out
+}
+
+/// Writes the example settings, cron snippet and README stub into `dir`.
+/// Existing files are left untouched; returns the files that were written.
+pub fn write_example_scaffold(
+    paths: &StorePaths,
+    dir: &Path,
+    executable: &str,
+) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create directory: {}", dir.display()))?;
+
+    let files = [
+        (SETTINGS_EXAMPLE_FILE, render_settings_example()?),
+        (CRON_EXAMPLE_FILE, render_cron_example(paths, executable)),
+        (README_STUB_FILE, render_readme_stub(paths)),
+    ];
+
+    let mut written = Vec::new();
+    for (name, content) in files {
+        let path = dir.join(name);
+        if path.exists() {
+            continue;
+        }
+        fs::write(&path, content)
+            .with_context(|| format!("failed to write file: {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CRON_EXAMPLE_FILE, FIELD_DOCS, README_STUB_FILE, SETTINGS_EXAMPLE_FILE,
+        render_settings_example, write_example_scaffold,
+    };
+    use crate::models::AppSettings;
+    use crate::store::StorePaths;
+    use serde_json::Value;
+    use std::fs;
+
+    fn strip_line_comments(content: &str) -> String {
+        content
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("//"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn every_settings_field_is_documented() {
+        let Value::Object(fields) = serde_json::to_value(AppSettings::default()).unwrap() else {
+            panic!("settings should serialize to an object");
+        };
+        let mut documented: Vec<&str> = FIELD_DOCS.iter().map(|(name, _)| *name).collect();
+        documented.sort_unstable();
+        let fields: Vec<&str> = fields.keys().map(String::as_str).collect();
+        assert_eq!(documented, fields);
+    }
+
+    #[test]
+    fn settings_example_parses_back_to_defaults() {
+        let example = render_settings_example().unwrap();
+        assert!(example.contains("  // Review command run in the PR checkout"));
+
+        let parsed: AppSettings = serde_json::from_str(&strip_line_comments(&example)).unwrap();
+        assert_eq!(
+            serde_json::to_value(parsed).unwrap(),
+            serde_json::to_value(AppSettings::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn scaffold_keeps_existing_files() {
+        let root = std::env::temp_dir().join(format!(
+            "pr-reviewer-scaffold-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let paths = StorePaths::with_root(&root).unwrap();
+        let out = root.join("example");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join(README_STUB_FILE), "mine").unwrap();
+
+        let written = write_example_scaffold(&paths, &out, "pr-reviewer-cli").unwrap();
+
+        assert_eq!(
+            written,
+            vec![out.join(SETTINGS_EXAMPLE_FILE), out.join(CRON_EXAMPLE_FILE)]
+        );
+        assert_eq!(
+            fs::read_to_string(out.join(README_STUB_FILE)).unwrap(),
+            "mine"
+        );
+        let cron = fs::read_to_string(out.join(CRON_EXAMPLE_FILE)).unwrap();
+        assert!(cron.contains(&format!("PR_REVIEWER_HOME={}", root.display())));
+        assert!(cron.contains("pr-reviewer-cli --quiet run"));
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,15 +1,19 @@
 use anyhow::anyhow;
 use chrono::{Local, Utc};
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::fs;
 use std::io::{BufRead, BufReader, IsTerminal, Write};
+use std::os::unix::process::CommandExt;
 use std::path::Path;
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc;
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Condvar, Mutex, OnceLock};
 use std::time::Duration;
 
-use crate::models::{EngineState, OpenPr};
+use regex::Regex;
+
+use crate::models::{CommandFallback, CommandWrapperScope, EngineState, OpenPr, PushOutcome};
 
 #[derive(Debug, Clone)]
 pub struct CommandResult {
@@ -25,16 +29,25 @@ pub enum ExecError {
         command: String,
         result: CommandResult,
     },
+    /// Killed after producing no output for `seconds` (`idle_timeout_seconds`).
+    IdleTimeout {
+        command: String,
+        seconds: u64,
+    },
 }
 
 impl std::fmt::Display for ExecError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Io(message) => write!(f, "{message}"),
+        let message = match self {
+            Self::Io(message) => message.clone(),
             Self::NonZero { command, result } => {
-                write!(f, "command failed: {command} (exit {})", result.exit_code)
+                format!("command failed: {command} (exit {})", result.exit_code)
             }
-        }
+            Self::IdleTimeout { command, seconds } => {
+                format!("command produced no output for {seconds}s: {command}")
+            }
+        };
+        write!(f, "{}", redact_github_token(&message))
     }
 }
 
@@ -43,14 +56,15 @@ impl std::error::Error for ExecError {}
 #[derive(Debug, Clone)]
 struct MonthlyFixCounter {
     month_key: String,
-    pr_numbers: HashSet<u64>,
+    /// `(repo key, PR number)`, so equal numbers in different repos count apart.
+    prs: HashSet<(Option<String>, u64)>,
 }
 
 impl MonthlyFixCounter {
     fn empty_for_current_month() -> Self {
         Self {
             month_key: current_month_key(),
-            pr_numbers: HashSet::new(),
+            prs: HashSet::new(),
         }
     }
 
@@ -58,17 +72,30 @@ impl MonthlyFixCounter {
         let now_key = current_month_key();
         if self.month_key != now_key {
             self.month_key = now_key;
-            self.pr_numbers.clear();
+            self.prs.clear();
         }
     }
 }
 
-fn current_month_key() -> String {
+pub fn current_month_key() -> String {
     Local::now().format("%Y-%m").to_string()
 }
 
+static COLOR_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns ANSI colors off for the rest of the process (`--no-color`).
+pub fn disable_color() {
+    COLOR_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether `--no-color` was given; `NO_COLOR` and non-terminals are checked separately.
+pub fn color_disabled() -> bool {
+    COLOR_DISABLED.load(Ordering::Relaxed)
+}
+
 fn ansi_color_enabled() -> bool {
-    std::io::stdout().is_terminal()
+    !color_disabled()
+        && std::io::stdout().is_terminal()
         && std::env::var_os("NO_COLOR").is_none()
         && std::env::var("TERM").map(|v| v != "dumb").unwrap_or(false)
 }
@@ -102,31 +129,27 @@ fn monthly_fix_counter() -> &'static Mutex<MonthlyFixCounter> {
 
 pub fn initialize_monthly_fix_counter(state: &EngineState) {
     let month_key = current_month_key();
-    let pr_numbers = state
-        .monthly_fixed_pr_numbers_by_month
-        .get(&month_key)
-        .cloned()
-        .unwrap_or_default()
-        .into_iter()
-        .collect::<HashSet<_>>();
+    let prs = state.monthly_fixed_prs(&month_key).into_iter().collect();
     if let Ok(mut counter) = monthly_fix_counter().lock() {
         counter.month_key = month_key;
-        counter.pr_numbers = pr_numbers;
+        counter.prs = prs;
     }
 }
 
 pub fn monthly_fixed_pr_count() -> usize {
     if let Ok(mut counter) = monthly_fix_counter().lock() {
         counter.rotate_if_needed();
-        return counter.pr_numbers.len();
+        return counter.prs.len();
     }
     0
 }
 
-pub fn record_monthly_fixed_pr(pr_number: u64) -> bool {
+/// Counts PR `pr_number` of `repo` (a `RepoTarget` key) as fixed this month.
+/// Returns false when it already was.
+pub fn record_monthly_fixed_pr(repo: Option<&str>, pr_number: u64) -> bool {
     if let Ok(mut counter) = monthly_fix_counter().lock() {
         counter.rotate_if_needed();
-        return counter.pr_numbers.insert(pr_number);
+        return counter.prs.insert((repo.map(str::to_string), pr_number));
     }
     false
 }
@@ -134,14 +157,65 @@ pub fn record_monthly_fixed_pr(pr_number: u64) -> bool {
 pub fn sync_monthly_fix_counter_into_state(state: &mut EngineState) {
     if let Ok(mut counter) = monthly_fix_counter().lock() {
         counter.rotate_if_needed();
-        let mut prs: Vec<u64> = counter.pr_numbers.iter().copied().collect();
-        prs.sort_unstable();
-        state
-            .monthly_fixed_pr_numbers_by_month
-            .insert(counter.month_key.clone(), prs);
+        state.set_monthly_fixed_prs(&counter.month_key, counter.prs.iter().cloned());
     }
 }
 
+/// Pid of the running command and whether it leads its own process group.
+fn running_child() -> &'static Mutex<Option<(u32, bool)>> {
+    static CHILD: OnceLock<Mutex<Option<(u32, bool)>>> = OnceLock::new();
+    CHILD.get_or_init(|| Mutex::new(None))
+}
+
+/// Records the pid of the command currently being executed so an interrupt
+/// handler can kill it; cleared again on drop.
+struct RunningChildGuard;
+
+impl RunningChildGuard {
+    fn track(pid: u32, process_group: bool) -> Self {
+        if let Ok(mut current) = running_child().lock() {
+            *current = Some((pid, process_group));
+        }
+        Self
+    }
+}
+
+impl Drop for RunningChildGuard {
+    fn drop(&mut self) {
+        if let Ok(mut current) = running_child().lock() {
+            *current = None;
+        }
+    }
+}
+
+/// Kills the currently running child command, if any.
+pub fn kill_running_child() {
+    let pid = running_child()
+        .lock()
+        .ok()
+        .and_then(|mut current| current.take());
+    if let Some((pid, process_group)) = pid {
+        signal_child("-TERM", pid, process_group);
+    }
+}
+
+/// Sends `signal` to `pid`, or to its whole process group so that programs the
+/// shell started (codex, git) go down with it.
+fn signal_child(signal: &str, pid: u32, process_group: bool) {
+    let target = if process_group {
+        format!("-{pid}")
+    } else {
+        pid.to_string()
+    };
+    let _ = Command::new("kill")
+        .arg(signal)
+        .arg("--")
+        .arg(target)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
 pub fn sh_quote(input: &str) -> String {
     format!("'{}'", input.replace('\'', "'\\''"))
 }
@@ -151,9 +225,286 @@ pub fn run_shell(
     cwd: Option<&str>,
     fail_on_non_zero: bool,
 ) -> std::result::Result<CommandResult, ExecError> {
-    run_shell_internal(command, cwd, fail_on_non_zero, false, None, false)
+    run_shell_internal(
+        command,
+        cwd,
+        fail_on_non_zero,
+        false,
+        None,
+        false,
+        OutputLimits::default(),
+    )
+}
+
+/// Removes ANSI escape sequences (SGR colors, cursor movement, OSC titles/links)
+/// from captured output, keeping the visible text.
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters and intermediates up to a final byte in @..~
+            Some('[') => {
+                for next in chars.by_ref() {
+                    if ('@'..='~').contains(&next) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(next) = chars.next() {
+                    if next == '\x07' {
+                        break;
+                    }
+                    if next == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Limits applied while a command runs; the default applies none.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputLimits {
+    /// Kill the command after this long without output.
+    pub idle_timeout: Option<Duration>,
+    /// Bytes of stdout and of stderr kept in the `CommandResult`; 0 keeps all.
+    pub max_captured_bytes: usize,
+}
+
+/// Start of the line that replaces output dropped by `OutputLimits::max_captured_bytes`.
+pub const TRUNCATION_MARKER: &str = "[pr-reviewer: output truncated";
+
+/// Captured command output that keeps at most `limit` bytes: the first half and
+/// the most recent lines, with a marker line in place of the dropped middle.
+struct CaptureBuffer {
+    limit: usize,
+    head: String,
+    tail: VecDeque<String>,
+    tail_bytes: usize,
+    omitted_bytes: usize,
+}
+
+impl CaptureBuffer {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            head: String::new(),
+            tail: VecDeque::new(),
+            tail_bytes: 0,
+            omitted_bytes: 0,
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        let size = line.len() + 1;
+        let in_head = self.tail.is_empty() && self.omitted_bytes == 0;
+        if self.limit == 0 || (in_head && self.head.len() + size <= self.limit / 2) {
+            self.head.push_str(line);
+            self.head.push('\n');
+            return;
+        }
+        self.tail.push_back(format!("{line}\n"));
+        self.tail_bytes += size;
+        let tail_limit = self.limit - self.limit / 2;
+        while self.tail_bytes > tail_limit {
+            let Some(dropped) = self.tail.pop_front() else {
+                break;
+            };
+            self.tail_bytes -= dropped.len();
+            self.omitted_bytes += dropped.len();
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut out = self.head;
+        if self.omitted_bytes > 0 {
+            out.push_str(&format!(
+                "{TRUNCATION_MARKER}, {} bytes omitted]\n",
+                self.omitted_bytes
+            ));
+        }
+        out.extend(self.tail);
+        out
+    }
+}
+
+/// Template every matching command is substituted into, e.g. to run codex in a
+/// container: `docker run --rm -v /src/app:/repo -w /repo image sh -c {COMMAND}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandWrapper {
+    pub template: String,
+    pub scope: CommandWrapperScope,
+}
+
+/// Placeholder in `CommandWrapper::template` replaced by the quoted command.
+pub const WRAPPED_COMMAND_PLACEHOLDER: &str = "{COMMAND}";
+
+impl CommandWrapper {
+    fn applies_to(&self, command: &str) -> bool {
+        match self.scope {
+            CommandWrapperScope::All => true,
+            CommandWrapperScope::Codex => runs_codex(command),
+        }
+    }
+
+    /// `command` nested into the template as one shell-quoted word.
+    pub fn wrap(&self, command: &str) -> String {
+        self.template
+            .replace(WRAPPED_COMMAND_PLACEHOLDER, &sh_quote(command))
+    }
+}
+
+/// Whether the first word after any `VAR=value` assignments is `codex`.
+fn runs_codex(command: &str) -> bool {
+    command
+        .split_whitespace()
+        .find(|word| !word.contains('='))
+        .is_some_and(|word| word == "codex" || word.ends_with("/codex"))
+}
+
+fn command_wrapper() -> &'static Mutex<Option<CommandWrapper>> {
+    static WRAPPER: OnceLock<Mutex<Option<CommandWrapper>>> = OnceLock::new();
+    WRAPPER.get_or_init(|| Mutex::new(None))
+}
+
+/// Keeps a command wrapper installed for `run_shell_internal`; removed on drop.
+pub struct CommandWrapperGuard;
+
+pub fn install_command_wrapper(wrapper: Option<CommandWrapper>) -> CommandWrapperGuard {
+    if let Ok(mut current) = command_wrapper().lock() {
+        *current = wrapper;
+    }
+    CommandWrapperGuard
+}
+
+impl Drop for CommandWrapperGuard {
+    fn drop(&mut self) {
+        if let Ok(mut current) = command_wrapper().lock() {
+            *current = None;
+        }
+    }
+}
+
+fn wrapped_command(command: &str) -> String {
+    command_wrapper()
+        .lock()
+        .ok()
+        .and_then(|current| {
+            current
+                .as_ref()
+                .filter(|wrapper| wrapper.applies_to(command))
+                .map(|wrapper| wrapper.wrap(command))
+        })
+        .unwrap_or_else(|| command.to_string())
+}
+
+fn github_token() -> &'static Mutex<Option<String>> {
+    static TOKEN: OnceLock<Mutex<Option<String>>> = OnceLock::new();
+    TOKEN.get_or_init(|| Mutex::new(None))
+}
+
+/// Exports `token` (`github_token`) as `GH_TOKEN` to every command run after this;
+/// `None` leaves the inherited environment and `gh auth` login alone.
+pub fn set_github_token(token: Option<String>) {
+    if let Ok(mut current) = github_token().lock() {
+        *current = token.filter(|token| !token.trim().is_empty());
+    }
+}
+
+fn current_github_token() -> Option<String> {
+    github_token()
+        .lock()
+        .ok()
+        .and_then(|current| current.clone())
+}
+
+/// Counting semaphore over output reader threads (`max_stream_threads`); a
+/// limit of 0 never blocks.
+pub struct ReaderSlots {
+    limit: AtomicUsize,
+    in_use: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl ReaderSlots {
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit),
+            in_use: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+        self.freed.notify_all();
+    }
+
+    /// Blocks until `count` slots are free and takes them together, so two
+    /// commands never each hold half of what they need. A request larger than
+    /// the limit is let through once nothing else holds a slot.
+    pub fn acquire(&'static self, count: usize) -> ReaderPermit {
+        let mut in_use = self.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let limit = self.limit.load(Ordering::Relaxed);
+            if limit == 0 || *in_use == 0 || *in_use + count <= limit {
+                break;
+            }
+            in_use = self.freed.wait(in_use).unwrap_or_else(|e| e.into_inner());
+        }
+        *in_use += count;
+        ReaderPermit { slots: self, count }
+    }
+
+    pub fn in_use(&self) -> usize {
+        *self.in_use.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Slots taken by `ReaderSlots::acquire`; given back on drop.
+pub struct ReaderPermit {
+    slots: &'static ReaderSlots,
+    count: usize,
+}
+
+impl Drop for ReaderPermit {
+    fn drop(&mut self) {
+        let mut in_use = self.slots.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        *in_use = in_use.saturating_sub(self.count);
+        self.slots.freed.notify_all();
+    }
 }
 
+static STREAM_READER_SLOTS: ReaderSlots = ReaderSlots::new(0);
+
+/// Caps the output reader threads of all commands running in this process at
+/// `limit` (`max_stream_threads`, two per command); 0 removes the cap.
+pub fn set_max_stream_threads(limit: usize) {
+    STREAM_READER_SLOTS.set_limit(limit);
+}
+
+/// `text` with the exported `github_token` replaced by `***`, for logs and errors.
+pub fn redact_github_token(text: &str) -> String {
+    match current_github_token() {
+        Some(token) => text.replace(&token, "***"),
+        None => text.to_string(),
+    }
+}
+
+/// Runs `command` via zsh, inside the installed `CommandWrapper` when it applies.
+/// With `idle_timeout`, output is read line by line (also when not streamed) and
+/// the command is killed once it stays silent that long.
 pub fn run_shell_internal(
     command: &str,
     cwd: Option<&str>,
@@ -161,19 +512,34 @@ pub fn run_shell_internal(
     stream_output: bool,
     stream_prefix: Option<&str>,
     compact_stream: bool,
+    limits: OutputLimits,
 ) -> std::result::Result<CommandResult, ExecError> {
+    let idle_timeout = limits.idle_timeout;
     let mut cmd = Command::new("/bin/zsh");
-    cmd.arg("-lc").arg(command);
+    cmd.arg("-lc").arg(wrapped_command(command));
+    if let Some(token) = current_github_token() {
+        cmd.env("GH_TOKEN", token);
+    }
     if let Some(dir) = cwd {
         cmd.current_dir(dir);
     }
 
-    let result = if stream_output {
+    let result = if stream_output || idle_timeout.is_some() || limits.max_captured_bytes > 0 {
+        if !stream_output {
+            cmd.stdin(Stdio::null());
+        }
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
+        // Its own process group, so an idle timeout can kill the programs the
+        // shell started, which would otherwise keep the output pipes open.
+        cmd.process_group(0);
+        // Held until this function returns, not by the reader threads: a reader
+        // stuck on a pipe a leftover process keeps open must not keep the slots.
+        let _permit = STREAM_READER_SLOTS.acquire(2);
         let mut child = cmd.spawn().map_err(|e| {
             ExecError::Io(format!("failed to execute command: {command}, error: {e}"))
         })?;
+        let _running = RunningChildGuard::track(child.id(), true);
 
         let stdout = child.stdout.take().ok_or_else(|| {
             ExecError::Io(format!("failed to capture stdout for command: {command}"))
@@ -200,20 +566,44 @@ pub fn run_shell_internal(
         });
         drop(tx);
 
-        let mut out_buf = String::new();
-        let mut err_buf = String::new();
+        let mut out_buf = CaptureBuffer::new(limits.max_captured_bytes);
+        let mut err_buf = CaptureBuffer::new(limits.max_captured_bytes);
         let use_compact_stream = compact_stream
             && stream_prefix.is_some()
             && std::io::stdout().is_terminal()
             && std::env::var("TERM").map(|v| v != "dumb").unwrap_or(false);
         let mut compact_renderer = use_compact_stream.then(|| CompactStepRenderer::new(5));
-        for (is_stdout, line) in rx {
+        loop {
+            let (is_stdout, line) = match idle_timeout {
+                Some(limit) => match rx.recv_timeout(limit) {
+                    Ok(message) => message,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        if let Some(renderer) = compact_renderer.as_mut() {
+                            renderer.clear();
+                        }
+                        signal_child("-KILL", child.id(), true);
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return Err(ExecError::IdleTimeout {
+                            command: command.to_string(),
+                            seconds: limit.as_secs(),
+                        });
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                },
+                None => match rx.recv() {
+                    Ok(message) => message,
+                    Err(_) => break,
+                },
+            };
+            // Captured output is stored without colors; the live stream keeps them.
             if is_stdout {
-                out_buf.push_str(&line);
-                out_buf.push('\n');
+                out_buf.push_line(&strip_ansi(&line));
             } else {
-                err_buf.push_str(&line);
-                err_buf.push('\n');
+                err_buf.push_line(&strip_ansi(&line));
+            }
+            if !stream_output {
+                continue;
             }
 
             if let Some(renderer) = compact_renderer.as_mut() {
@@ -240,13 +630,20 @@ pub fn run_shell_internal(
             .map_err(|e| ExecError::Io(format!("failed waiting command: {command}, error: {e}")))?;
         CommandResult {
             exit_code: status.code().unwrap_or(-1),
-            stdout: out_buf,
-            stderr: err_buf,
+            stdout: out_buf.finish(),
+            stderr: err_buf.finish(),
         }
     } else {
-        let output = cmd.output().map_err(|e| {
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        let child = cmd.spawn().map_err(|e| {
             ExecError::Io(format!("failed to execute command: {command}, error: {e}"))
         })?;
+        let _running = RunningChildGuard::track(child.id(), false);
+        let output = child
+            .wait_with_output()
+            .map_err(|e| ExecError::Io(format!("failed waiting command: {command}, error: {e}")))?;
 
         CommandResult {
             exit_code: output.status.code().unwrap_or(-1),
@@ -276,20 +673,25 @@ pub fn run_with_retry(
         cwd,
         retries,
         retry_delay_seconds,
+        &[],
         false,
         None,
         false,
+        OutputLimits::default(),
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_with_retry_streaming(
     command: &str,
     cwd: Option<&str>,
     retries: u8,
     retry_delay_seconds: u64,
+    retryable_exit_codes: &[i32],
     stream_output: bool,
     stream_prefix: Option<&str>,
     compact_stream: bool,
+    limits: OutputLimits,
 ) -> std::result::Result<CommandResult, ExecError> {
     let attempts = retries.max(1) as usize + 1;
     let mut last_err: Option<ExecError> = None;
@@ -302,12 +704,20 @@ pub fn run_with_retry_streaming(
             stream_output,
             stream_prefix,
             compact_stream,
+            limits,
         ) {
             Ok(result) => return Ok(result),
             Err(err) => {
+                let hinted_wait = secondary_rate_limit_wait(&err);
+                let retryable = hinted_wait.is_some() || should_retry(&err, retryable_exit_codes);
                 last_err = Some(err);
+                if !retryable {
+                    break;
+                }
                 if attempt < attempts {
-                    std::thread::sleep(Duration::from_secs(retry_delay_seconds.max(1)));
+                    std::thread::sleep(
+                        hinted_wait.unwrap_or(Duration::from_secs(retry_delay_seconds.max(1))),
+                    );
                 }
             }
         }
@@ -316,6 +726,105 @@ pub fn run_with_retry_streaming(
     Err(last_err.unwrap_or_else(|| ExecError::Io("unknown command failure".to_string())))
 }
 
+/// Longest wait a secondary rate-limit hint can ask for; larger hints are capped.
+const MAX_RETRY_AFTER_SECONDS: u64 = 900;
+
+/// Wait GitHub asks for when `err` is a secondary rate limit whose stderr has
+/// a hint such as `retry after 60 seconds` or `Retry-After: 60`, capped at
+/// `MAX_RETRY_AFTER_SECONDS`. Such errors are retried even though gh reports
+/// them as HTTP 403.
+fn secondary_rate_limit_wait(err: &ExecError) -> Option<Duration> {
+    let ExecError::NonZero { result, .. } = err else {
+        return None;
+    };
+    let stderr = result.stderr.to_ascii_lowercase();
+    if !stderr.contains("secondary rate limit") {
+        return None;
+    }
+    let hint =
+        Regex::new(r"retry[- ]after[:=]?\s*(\d+)(?:\s*(s|sec|secs|seconds?|m|mins?|minutes?)\b)?")
+            .expect("valid retry-after regex");
+    let captures = hint.captures(&stderr)?;
+    let value = captures[1].parse::<u64>().unwrap_or(u64::MAX);
+    let seconds = match captures.get(2).map(|unit| unit.as_str()) {
+        Some(unit) if unit.starts_with('m') => value.saturating_mul(60),
+        _ => value,
+    };
+    Some(Duration::from_secs(
+        seconds.clamp(1, MAX_RETRY_AFTER_SECONDS),
+    ))
+}
+
+/// Retry decision for `run_with_retry_streaming`: with `retryable_exit_codes`
+/// set, only those non-zero exits (and IO errors) are retried; an empty list
+/// falls back to `is_retryable`.
+pub fn should_retry(err: &ExecError, retryable_exit_codes: &[i32]) -> bool {
+    match err {
+        ExecError::NonZero { result, .. } if !retryable_exit_codes.is_empty() => {
+            retryable_exit_codes.contains(&result.exit_code)
+        }
+        _ => is_retryable(err),
+    }
+}
+
+const NON_RETRYABLE_MARKERS: &[&str] = &[
+    "authentication failed",
+    "bad credentials",
+    "gh auth login",
+    "not logged into",
+    "permission denied (publickey)",
+    "http 401",
+    "http 403",
+    "http 404",
+    "repository not found",
+    "could not resolve to a",
+    "not a git repository",
+    "no pull requests found",
+];
+
+const AUTH_FAILURE_MARKERS: &[&str] = &[
+    "authentication failed",
+    "permission denied (publickey)",
+    "host key verification failed",
+    "could not read username",
+    "terminal prompts disabled",
+    "http 401",
+    "http 403",
+];
+
+/// Returns true when a git command failed because the transport could not
+/// authenticate (missing SSH key, no HTTPS credentials, ...).
+pub fn is_auth_failure(err: &ExecError) -> bool {
+    match err {
+        ExecError::Io(_) | ExecError::IdleTimeout { .. } => false,
+        ExecError::NonZero { result, .. } => {
+            let output = format!("{}\n{}", result.stderr, result.stdout).to_ascii_lowercase();
+            AUTH_FAILURE_MARKERS
+                .iter()
+                .any(|marker| output.contains(marker))
+        }
+    }
+}
+
+/// Returns false for failures that will not go away on retry (auth problems,
+/// missing repositories/PRs, commands stuck on a prompt); everything else is
+/// treated as transient. Only stderr is inspected, since stdout may echo
+/// arbitrary program output, and rate-limit errors (which GitHub reports as
+/// `HTTP 403`) stay retryable.
+pub fn is_retryable(err: &ExecError) -> bool {
+    match err {
+        ExecError::Io(_) => true,
+        ExecError::IdleTimeout { .. } => false,
+        ExecError::NonZero { result, .. } => {
+            let stderr = result.stderr.to_ascii_lowercase();
+            stderr.contains("rate limit")
+                || !NON_RETRYABLE_MARKERS
+                    .iter()
+                    .any(|marker| stderr.contains(marker))
+        }
+    }
+}
+
 struct CompactStepRenderer {
     max_lines: usize,
     rendered_once: bool,
@@ -374,8 +883,11 @@ impl CompactStepRenderer {
 }
 
 pub fn render_exec_error(err: &ExecError) -> String {
-    match err {
+    let rendered = match err {
         ExecError::Io(message) => message.clone(),
+        ExecError::IdleTimeout { command, seconds } => {
+            format!("{command} killed after {seconds}s without output (idle_timeout_seconds)")
+        }
         ExecError::NonZero { command, result } => {
             let stderr = result.stderr.trim();
             if stderr.is_empty() {
@@ -384,40 +896,119 @@ pub fn render_exec_error(err: &ExecError) -> String {
                 format!("{command} failed with exit {}: {stderr}", result.exit_code)
             }
         }
-    }
+    };
+    redact_github_token(&rendered)
 }
 
-pub fn is_codex_review_prompt_conflict(err: &ExecError) -> bool {
-    match err {
-        ExecError::NonZero { command, result } => {
-            command.contains("codex review")
-                && command.contains("--base")
-                && result.stderr.contains("cannot be used with '[PROMPT]'")
-        }
-        _ => false,
-    }
+/// First `command_fallbacks` rule whose command and stderr substrings match a
+/// command that exited non-zero.
+pub fn find_command_fallback<'a>(
+    err: &ExecError,
+    rules: &'a [CommandFallback],
+) -> Option<&'a CommandFallback> {
+    let ExecError::NonZero { command, result } = err else {
+        return None;
+    };
+    rules.iter().find(|rule| {
+        !rule.match_stderr.is_empty()
+            && result.stderr.contains(&rule.match_stderr)
+            && command.contains(&rule.match_command_substring)
+    })
 }
 
-pub fn strip_co_authored_by_trailers(message: &str) -> String {
+/// Removes `Co-authored-by:` trailers. With an empty `patterns` list every trailer is
+/// removed; otherwise only trailers containing one of the patterns (case-insensitive,
+/// typically a bot email) are removed so human co-authors survive.
+pub fn strip_co_authored_by_trailers(message: &str, patterns: &[String]) -> String {
     let filtered = message
         .lines()
         .filter(|line| {
-            !line
-                .trim_start()
-                .to_ascii_lowercase()
-                .starts_with("co-authored-by:")
+            let lower = line.trim_start().to_ascii_lowercase();
+            if !lower.starts_with("co-authored-by:") {
+                return true;
+            }
+            if patterns.is_empty() {
+                return false;
+            }
+            !patterns
+                .iter()
+                .any(|pattern| lower.contains(&pattern.to_ascii_lowercase()))
         })
         .collect::<Vec<_>>()
         .join("\n");
     filtered.trim_end().to_string() + "\n"
 }
 
+/// Author/committer for the bot's commits; unset parts use the git config.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GitIdentity<'a> {
+    pub name: Option<&'a str>,
+    pub email: Option<&'a str>,
+}
+
+/// `git commit` reading its message from `message_file`, with hooks disabled and
+/// `identity` passed as `-c user.name=... -c user.email=...`.
+fn commit_command(identity: GitIdentity<'_>, amend: bool, message_file: &str) -> String {
+    let mut command = "git -c core.hooksPath=/dev/null".to_string();
+    if let Some(name) = identity.name {
+        command.push_str(&format!(" -c user.name={}", sh_quote(name)));
+    }
+    if let Some(email) = identity.email {
+        command.push_str(&format!(" -c user.email={}", sh_quote(email)));
+    }
+    command.push_str(if amend {
+        " commit --amend --no-verify -F "
+    } else {
+        " commit --no-verify -F "
+    });
+    command.push_str(&sh_quote(message_file));
+    command
+}
+
+/// Remote-tracking ref the latest commit is pushed to: `remote/branch` with an
+/// explicit push remote, else the branch's upstream.
+fn upstream_ref(remote: Option<&str>, branch: &str) -> String {
+    match remote {
+        Some(remote) => format!("{remote}/{branch}"),
+        None => "@{upstream}".to_string(),
+    }
+}
+
+/// Whether HEAD is already on `upstream` (`git log <upstream>..HEAD` lists
+/// nothing). A branch without that remote ref has not been pushed.
+fn head_is_pushed(repo_path: &str, upstream: &str) -> std::result::Result<bool, ExecError> {
+    match run_shell_internal(
+        &format!(
+            "git log --format=%H {}",
+            sh_quote(&format!("{upstream}..HEAD"))
+        ),
+        Some(repo_path),
+        true,
+        false,
+        None,
+        false,
+        OutputLimits::default(),
+    ) {
+        Ok(result) => Ok(result.stdout.trim().is_empty()),
+        Err(ExecError::NonZero { .. }) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Amends the latest commit to drop `Co-authored-by:` trailers; a commit that
+/// is already on `upstream` is left alone so pushed history is not rewritten.
 pub fn sanitize_latest_commit_message(
     repo_path: &str,
+    upstream: &str,
+    co_author_patterns: &[String],
+    identity: GitIdentity<'_>,
     stream_output: bool,
     stream_prefix: Option<&str>,
     compact_stream: bool,
 ) -> std::result::Result<(), ExecError> {
+    if head_is_pushed(repo_path, upstream)? {
+        return Ok(());
+    }
     let latest = run_shell_internal(
         "git log -1 --pretty=%B",
         Some(repo_path),
@@ -425,8 +1016,9 @@ pub fn sanitize_latest_commit_message(
         stream_output,
         stream_prefix,
         compact_stream,
+        OutputLimits::default(),
     )?;
-    let cleaned = strip_co_authored_by_trailers(&latest.stdout);
+    let cleaned = strip_co_authored_by_trailers(&latest.stdout, co_author_patterns);
     if cleaned.trim_end() == latest.stdout.trim_end() {
         return Ok(());
     }
@@ -445,15 +1037,13 @@ pub fn sanitize_latest_commit_message(
     })?;
 
     let amend = run_shell_internal(
-        &format!(
-            "git -c core.hooksPath=/dev/null commit --amend --no-verify -F {}",
-            sh_quote(&temp_file.display().to_string())
-        ),
+        &commit_command(identity, true, &temp_file.display().to_string()),
         Some(repo_path),
         true,
         stream_output,
         stream_prefix,
         compact_stream,
+        OutputLimits::default(),
     );
     let _ = fs::remove_file(&temp_file);
     amend.map(|_| ())
@@ -520,6 +1110,69 @@ fn parse_review_findings(text: &str) -> Vec<ReviewFinding> {
     findings
 }
 
+/// True when review output contains `clean_marker` (case-insensitive) and no
+/// `- [Pn]` findings. An empty marker never reports a clean review.
+pub fn review_is_clean(review_stdout: &str, clean_marker: &str) -> bool {
+    let marker = clean_marker.trim().to_ascii_lowercase();
+    if marker.is_empty() {
+        return false;
+    }
+    review_stdout.to_ascii_lowercase().contains(&marker)
+        && parse_review_findings(review_stdout).is_empty()
+}
+
+/// Token/cost usage reported by a command, parsed with the `usage_pattern` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageSummary {
+    pub tokens_used: Option<u64>,
+    pub estimated_cost: Option<f64>,
+}
+
+impl UsageSummary {
+    pub fn merge(self, other: Self) -> Self {
+        let sum_tokens = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+        let sum_cost = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+        };
+        Self {
+            tokens_used: sum_tokens(self.tokens_used, other.tokens_used),
+            estimated_cost: sum_cost(self.estimated_cost, other.estimated_cost),
+        }
+    }
+}
+
+/// Sums the `tokens` / `cost` named groups of every `pattern` match in `output`.
+/// A non-matching pattern yields an empty summary.
+pub fn parse_usage(output: &str, pattern: &Regex) -> UsageSummary {
+    pattern
+        .captures_iter(output)
+        .map(|captures| UsageSummary {
+            tokens_used: captures
+                .name("tokens")
+                .and_then(|value| value.as_str().replace([',', '_'], "").parse().ok()),
+            estimated_cost: captures
+                .name("cost")
+                .and_then(|value| value.as_str().replace(',', "").parse().ok()),
+        })
+        .fold(UsageSummary::default(), UsageSummary::merge)
+}
+
+/// First session id `pattern` finds in `output`: its `session` group, or the
+/// whole match when it has none. An invalid or non-matching pattern yields `None`.
+pub fn parse_session_id(output: &str, pattern: &str) -> Option<String> {
+    let captures = Regex::new(pattern).ok()?.captures(output)?;
+    let id = captures
+        .name("session")
+        .or_else(|| captures.get(0))?
+        .as_str()
+        .trim();
+    (!id.is_empty()).then(|| id.to_string())
+}
+
 fn highest_issue_level_from_findings(findings: &[ReviewFinding]) -> String {
     let best = findings.iter().map(|item| item.issue_level).min();
     best.map(|value| format!("P{value}"))
@@ -614,6 +1267,20 @@ fn summarize_change_from_findings(findings: &[ReviewFinding]) -> Option<String>
     ))
 }
 
+/// JSON reports keep the command output in escaped strings; unwrap them so
+/// findings can be parsed the same way as markdown/text reports.
+fn report_output_text(content: String) -> String {
+    let Ok(serde_json::Value::Object(report)) = serde_json::from_str::<serde_json::Value>(&content)
+    else {
+        return content;
+    };
+    ["stdout", "stderr"]
+        .iter()
+        .filter_map(|key| report.get(*key).and_then(|value| value.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn derive_commit_context_from_report(report_path: Option<&Path>) -> (String, String) {
     let default_summary = "Apply automated fixes based on review findings.".to_string();
 
@@ -621,7 +1288,7 @@ fn derive_commit_context_from_report(report_path: Option<&Path>) -> (String, Str
         return (default_summary, "Unknown".to_string());
     };
     let content = match fs::read_to_string(path) {
-        Ok(value) => value,
+        Ok(value) => report_output_text(value),
         Err(_) => return (default_summary, "Unknown".to_string()),
     };
 
@@ -719,23 +1386,103 @@ END_COMMIT_MESSAGE",
         pr_title = pr.title
     );
     let command = format!("codex exec {}", sh_quote(&prompt));
-    let result = run_shell_internal(&command, Some(repo_path), false, false, None, false).ok()?;
+    let result = run_shell_internal(
+        &command,
+        Some(repo_path),
+        false,
+        false,
+        None,
+        false,
+        OutputLimits::default(),
+    )
+    .ok()?;
     if result.exit_code != 0 {
         return None;
     }
     extract_codex_commit_message(&result.stdout)
 }
 
+/// Message of the single commit replacing the fix step's own commits.
+pub const SQUASH_COMMIT_MESSAGE: &str = "chore: auto-fix";
+
+/// Commits made on top of `base`, e.g. by codex during the fix step, undone
+/// with `git reset --soft` so their changes get staged for one new commit.
+/// Commits up to `base` (the PR author's) stay. Returns whether any were undone.
+fn squash_commits_since(
+    repo_path: &str,
+    base: &str,
+    stream_output: bool,
+    stream_prefix: Option<&str>,
+    compact_stream: bool,
+) -> std::result::Result<bool, ExecError> {
+    let run = |command: &str| {
+        run_shell_internal(
+            command,
+            Some(repo_path),
+            true,
+            stream_output,
+            stream_prefix,
+            compact_stream,
+            OutputLimits::default(),
+        )
+    };
+    let count = run(&format!("git rev-list --count {}", squash_range(base)))?;
+    if count.stdout.trim() == "0" {
+        return Ok(false);
+    }
+    run(&format!("git reset --soft {}", sh_quote(base)))?;
+    Ok(true)
+}
+
+/// Revision range of the commits `squash_commits_since` replaces.
+fn squash_range(base: &str) -> String {
+    sh_quote(&format!("{base}..HEAD"))
+}
+
+/// How `commit_and_push_if_needed` commits and pushes a fix.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommitOptions<'a> {
+    /// Trailer patterns to strip after committing; `None` leaves the commit
+    /// message untouched.
+    pub strip_co_authors: Option<&'a [String]>,
+    pub identity: GitIdentity<'a>,
+    /// Commits made after it are folded into the fix commit
+    /// (`SQUASH_COMMIT_MESSAGE`).
+    pub squash_base: Option<&'a str>,
+    /// Remote the PR branch is pushed to (see `push_command`).
+    pub push_remote: Option<&'a str>,
+    pub retries: u8,
+    pub retry_delay_seconds: u64,
+}
+
+/// Commits the fix when the working tree changed and pushes it to the PR branch.
 pub fn commit_and_push_if_needed(
     pr: &OpenPr,
     report_path: Option<&Path>,
     repo_path: &str,
-    retries: u8,
-    retry_delay_seconds: u64,
+    options: CommitOptions<'_>,
     stream_output: bool,
     stream_prefix: Option<&str>,
     compact_stream: bool,
-) -> std::result::Result<bool, ExecError> {
+) -> std::result::Result<PushOutcome, ExecError> {
+    let CommitOptions {
+        strip_co_authors,
+        identity,
+        squash_base,
+        push_remote,
+        retries,
+        retry_delay_seconds,
+    } = options;
+    let squashed = match squash_base {
+        Some(base) => squash_commits_since(
+            repo_path,
+            base,
+            stream_output,
+            stream_prefix,
+            compact_stream,
+        )?,
+        None => false,
+    };
     let status = run_shell_internal(
         "git status --porcelain",
         Some(repo_path),
@@ -743,9 +1490,10 @@ pub fn commit_and_push_if_needed(
         stream_output,
         stream_prefix,
         compact_stream,
+        OutputLimits::default(),
     )?;
     if status.stdout.trim().is_empty() {
-        return Ok(false);
+        return Ok(PushOutcome::NoChanges);
     }
 
     run_shell_internal(
@@ -755,13 +1503,18 @@ pub fn commit_and_push_if_needed(
         stream_output,
         stream_prefix,
         compact_stream,
+        OutputLimits::default(),
     )?;
     let fallback_message = || {
         let (summary, issue_level) = derive_commit_context_from_report(report_path);
         build_commit_message(pr.number, &issue_level, &summary)
     };
-    let commit_message = generate_commit_message_with_codex(pr, report_path, repo_path)
-        .unwrap_or_else(fallback_message);
+    let commit_message = if squashed {
+        SQUASH_COMMIT_MESSAGE.to_string()
+    } else {
+        generate_commit_message_with_codex(pr, report_path, repo_path)
+            .unwrap_or_else(fallback_message)
+    };
     let temp_file = std::env::temp_dir().join(format!(
         "pr-reviewer-commit-msg-{}-{}.txt",
         std::process::id(),
@@ -776,31 +1529,75 @@ pub fn commit_and_push_if_needed(
     })?;
 
     let commit_result = run_shell_internal(
-        &format!(
-            "git -c core.hooksPath=/dev/null commit --no-verify -F {}",
-            sh_quote(&temp_file.display().to_string())
-        ),
+        &commit_command(identity, false, &temp_file.display().to_string()),
         Some(repo_path),
         true,
         stream_output,
         stream_prefix,
         compact_stream,
+        OutputLimits::default(),
     );
     let _ = fs::remove_file(&temp_file);
     commit_result?;
-    sanitize_latest_commit_message(repo_path, stream_output, stream_prefix, compact_stream)?;
+    if let Some(patterns) = strip_co_authors {
+        sanitize_latest_commit_message(
+            repo_path,
+            &upstream_ref(push_remote, &pr.head_ref_name),
+            patterns,
+            identity,
+            stream_output,
+            stream_prefix,
+            compact_stream,
+        )?;
+    }
 
+    let remote_before = rev_parse(repo_path, &upstream_ref(push_remote, &pr.head_ref_name));
     run_with_retry_streaming(
-        "git push",
+        &push_command(push_remote, &pr.head_ref_name),
         Some(repo_path),
         retries,
         retry_delay_seconds,
+        &[],
         stream_output,
         stream_prefix,
         compact_stream,
+        OutputLimits::default(),
     )?;
 
-    Ok(true)
+    Ok(push_outcome(
+        rev_parse(repo_path, "HEAD").as_deref(),
+        remote_before.as_deref(),
+    ))
+}
+
+/// `git push` to the checked-out branch's upstream, or with `remote` (a plain
+/// `remote_name`) to the PR branch `branch` on that remote.
+fn push_command(remote: Option<&str>, branch: &str) -> String {
+    match remote {
+        Some(remote) => format!("git push {remote} {}", sh_quote(&format!("HEAD:{branch}"))),
+        None => "git push".to_string(),
+    }
+}
+
+/// Commit `rev` resolves to in `repo_path`, or `None` when it does not exist.
+fn rev_parse(repo_path: &str, rev: &str) -> Option<String> {
+    let result = run_shell(
+        &format!("git rev-parse --verify --quiet {}", sh_quote(rev)),
+        Some(repo_path),
+        false,
+    )
+    .ok()?;
+    let commit = result.stdout.trim();
+    (result.exit_code == 0 && !commit.is_empty()).then(|| commit.to_string())
+}
+
+/// `git push` had nothing to send when the remote-tracking ref of the PR
+/// branch already pointed at `HEAD` before it.
+fn push_outcome(head: Option<&str>, remote_before: Option<&str>) -> PushOutcome {
+    match (head, remote_before) {
+        (Some(head), Some(remote)) if head == remote => PushOutcome::Committed,
+        _ => PushOutcome::Pushed,
+    }
 }
 
 pub fn anyhow_from_exec(err: ExecError) -> anyhow::Error {
@@ -810,10 +1607,495 @@ pub fn anyhow_from_exec(err: ExecError) -> anyhow::Error {
 #[cfg(test)]
 mod tests {
     use super::{
-        build_commit_message, derive_commit_context_from_report, extract_codex_commit_message,
-        format_summary_with_level, infer_issue_level_from_text, parse_review_findings,
-        summarize_change_from_findings,
+        CaptureBuffer, CommandResult, CommandWrapper, CommitOptions, ExecError, GitIdentity,
+        MAX_RETRY_AFTER_SECONDS, OutputLimits, ReaderSlots, TRUNCATION_MARKER, UsageSummary,
+        build_commit_message, commit_and_push_if_needed, commit_command,
+        derive_commit_context_from_report, extract_codex_commit_message, find_command_fallback,
+        format_summary_with_level, infer_issue_level_from_text, is_auth_failure, is_retryable,
+        parse_review_findings, parse_session_id, parse_usage, push_command, push_outcome,
+        record_monthly_fixed_pr, render_exec_error, report_output_text, rev_parse, review_is_clean,
+        run_shell, run_shell_internal, sanitize_latest_commit_message, secondary_rate_limit_wait,
+        set_github_token, should_retry, squash_commits_since, squash_range, strip_ansi,
+        strip_co_authored_by_trailers, summarize_change_from_findings, upstream_ref,
     };
+    use crate::models::{
+        CommandFallback, CommandWrapperScope, OpenPr, PushOutcome, default_command_fallbacks,
+    };
+    use regex::Regex;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    const MESSAGE_WITH_CO_AUTHORS: &str = "\
+fix: handle empty schedule\n\
+\n\
+Co-authored-by: Codex <codex@users.noreply.github.com>\n\
+Co-Authored-By: Jane Doe <jane@example.com>\n";
+
+    #[test]
+    fn monthly_fix_counter_keeps_equal_numbers_of_different_repos() {
+        assert!(record_monthly_fixed_pr(Some("counter-test-web"), 5));
+        assert!(record_monthly_fixed_pr(Some("counter-test-api"), 5));
+        assert!(!record_monthly_fixed_pr(Some("counter-test-web"), 5));
+    }
+
+    #[test]
+    fn strip_co_authored_by_trailers_removes_all_without_patterns() {
+        let cleaned = strip_co_authored_by_trailers(MESSAGE_WITH_CO_AUTHORS, &[]);
+        assert_eq!(cleaned, "fix: handle empty schedule\n");
+    }
+
+    #[test]
+    fn strip_co_authored_by_trailers_keeps_human_co_authors() {
+        let patterns = vec!["codex@users.noreply.github.com".to_string()];
+        let cleaned = strip_co_authored_by_trailers(MESSAGE_WITH_CO_AUTHORS, &patterns);
+        assert!(!cleaned.contains("Codex"));
+        assert!(cleaned.contains("Co-Authored-By: Jane Doe <jane@example.com>"));
+    }
+
+    fn non_zero(stderr: &str) -> ExecError {
+        ExecError::NonZero {
+            command: "gh pr list".to_string(),
+            result: CommandResult {
+                exit_code: 1,
+                stdout: String::new(),
+                stderr: stderr.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn is_retryable_rejects_auth_failures() {
+        assert!(!is_retryable(&non_zero(
+            "To get started with GitHub CLI, please run:  gh auth login"
+        )));
+        assert!(!is_retryable(&non_zero("HTTP 401: Bad credentials")));
+        assert!(!is_retryable(&non_zero(
+            "git@github.com: Permission denied (publickey)."
+        )));
+    }
+
+    #[test]
+    fn is_retryable_rejects_not_found() {
+        assert!(!is_retryable(&non_zero(
+            "GraphQL: Could not resolve to a Repository with the name 'org/missing'."
+        )));
+        assert!(!is_retryable(&non_zero(
+            "remote: Repository not found.\nfatal: repository 'https://github.com/org/x/' not found"
+        )));
+    }
+
+    #[test]
+    fn is_auth_failure_detects_ssh_and_https_auth_errors() {
+        assert!(is_auth_failure(&non_zero(
+            "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."
+        )));
+        assert!(is_auth_failure(&non_zero(
+            "fatal: could not read Username for 'https://github.com': terminal prompts disabled"
+        )));
+        assert!(!is_auth_failure(&non_zero(
+            "fatal: unable to access 'https://github.com/org/repo/': Could not resolve host: github.com"
+        )));
+        assert!(!is_auth_failure(&ExecError::Io("broken pipe".to_string())));
+    }
+
+    fn exit_with(code: i32, stderr: &str) -> ExecError {
+        ExecError::NonZero {
+            command: "codex exec fix".to_string(),
+            result: CommandResult {
+                exit_code: code,
+                stdout: String::new(),
+                stderr: stderr.to_string(),
+            },
+        }
+    }
+
+    /// Fresh, empty directory for the test `name`.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pr-reviewer-shell-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn push_outcome_compares_head_with_the_remote_branch() {
+        let dir = temp_dir("push-outcome");
+        let repo_path = dir.join("work");
+        std::fs::create_dir_all(&repo_path).unwrap();
+        let repo_path = repo_path.to_str().unwrap();
+        run_shell(
+            "git init -q --bare ../remote.git && git init -q && git remote add origin ../remote.git \
+             && git -c user.name=t -c user.email=t@t commit -q --allow-empty -m init \
+             && git push -q -u origin HEAD:feature",
+            Some(repo_path),
+            true,
+        )
+        .unwrap();
+        let outcome = || {
+            push_outcome(
+                rev_parse(repo_path, "HEAD").as_deref(),
+                rev_parse(repo_path, &upstream_ref(Some("origin"), "feature")).as_deref(),
+            )
+        };
+        assert_eq!(outcome(), PushOutcome::Committed);
+
+        run_shell(
+            "git -c user.name=t -c user.email=t@t commit -q --allow-empty -m fix",
+            Some(repo_path),
+            true,
+        )
+        .unwrap();
+        assert_eq!(outcome(), PushOutcome::Pushed);
+        assert_eq!(rev_parse(repo_path, "origin/missing"), None);
+        assert_eq!(push_outcome(Some("1a2b3c4"), None), PushOutcome::Pushed);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn commit_command_passes_bot_identity_when_configured() {
+        let identity = GitIdentity {
+            name: Some("pr-reviewer bot"),
+            email: Some("bot@example.com"),
+        };
+        assert_eq!(
+            commit_command(identity, false, "/tmp/msg.txt"),
+            "git -c core.hooksPath=/dev/null -c user.name='pr-reviewer bot' -c user.email='bot@example.com' commit --no-verify -F '/tmp/msg.txt'"
+        );
+        assert_eq!(
+            commit_command(GitIdentity::default(), true, "/tmp/msg.txt"),
+            "git -c core.hooksPath=/dev/null commit --amend --no-verify -F '/tmp/msg.txt'"
+        );
+        let email_only = GitIdentity {
+            email: Some("bot@example.com"),
+            ..GitIdentity::default()
+        };
+        let command = commit_command(email_only, false, "m");
+        assert!(command.contains(" -c user.email='bot@example.com' commit"));
+        assert!(!command.contains("user.name"));
+    }
+
+    #[test]
+    fn commit_and_push_reports_no_changes_for_clean_tree() {
+        let dir = temp_dir("clean");
+        let repo_path = dir.to_str().unwrap();
+        run_shell_internal(
+            "git init -q && git -c user.name=t -c user.email=t@t commit -q --allow-empty -m init",
+            Some(repo_path),
+            true,
+            false,
+            None,
+            false,
+            OutputLimits::default(),
+        )
+        .unwrap();
+
+        let outcome = commit_and_push_if_needed(
+            &OpenPr::default(),
+            None,
+            repo_path,
+            CommitOptions::default(),
+            false,
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(outcome, PushOutcome::NoChanges);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sanitize_skips_amend_when_commit_is_on_remote() {
+        let dir = temp_dir("sanitize");
+        std::fs::create_dir_all(dir.join("work")).unwrap();
+        let repo_path = dir.join("work");
+        let repo_path = repo_path.to_str().unwrap();
+        let identity = GitIdentity {
+            name: Some("t"),
+            email: Some("t@t"),
+        };
+        let git = "git -c user.name=t -c user.email=t@t";
+        run_shell(
+            &format!(
+                "git init -q --bare ../remote.git && git init -q && git remote add origin ../remote.git \
+                 && echo a > a.txt && git add -A \
+                 && {git} commit -q -m 'fix: a' -m 'Co-authored-by: Codex <codex@openai.com>' \
+                 && git push -q -u origin HEAD:feature"
+            ),
+            Some(repo_path),
+            true,
+        )
+        .unwrap();
+        let message = || {
+            run_shell("git log -1 --pretty=%B", Some(repo_path), true)
+                .unwrap()
+                .stdout
+        };
+
+        assert_eq!(upstream_ref(None, "feature"), "@{upstream}");
+        for upstream in [
+            upstream_ref(None, "feature"),
+            upstream_ref(Some("origin"), "feature"),
+        ] {
+            sanitize_latest_commit_message(repo_path, &upstream, &[], identity, false, None, false)
+                .unwrap();
+            assert!(message().contains("Co-authored-by: Codex"));
+        }
+
+        run_shell(
+            &format!(
+                "echo b > b.txt && git add -A \
+                 && {git} commit -q -m 'fix: b' -m 'Co-authored-by: Codex <codex@openai.com>'"
+            ),
+            Some(repo_path),
+            true,
+        )
+        .unwrap();
+        sanitize_latest_commit_message(
+            repo_path,
+            &upstream_ref(None, "feature"),
+            &[],
+            identity,
+            false,
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(message(), "fix: b\n\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn squash_commits_since_keeps_author_commits() {
+        assert_eq!(squash_range("3f2c1ab"), "'3f2c1ab..HEAD'");
+
+        let dir = temp_dir("squash");
+        let repo_path = dir.to_str().unwrap();
+        let git = "git -c user.name=t -c user.email=t@t";
+        let base = run_shell(
+            &format!(
+                "git init -q && echo a > a.txt && git add -A && {git} commit -q -m author \
+                 && git rev-parse HEAD \
+                 && echo b > b.txt && git add -A && {git} commit -q -m 'bot 1' \
+                 && echo c > c.txt && git add -A && {git} commit -q -m 'bot 2'"
+            ),
+            Some(repo_path),
+            true,
+        )
+        .unwrap()
+        .stdout
+        .trim()
+        .to_string();
+
+        assert!(squash_commits_since(repo_path, &base, false, None, false).unwrap());
+        let after = run_shell(
+            "git rev-parse HEAD && git log --format=%s && git diff --cached --name-only",
+            Some(repo_path),
+            true,
+        )
+        .unwrap()
+        .stdout;
+        assert_eq!(after, format!("{base}\nauthor\nb.txt\nc.txt\n"));
+        assert!(!squash_commits_since(repo_path, &base, false, None, false).unwrap());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn find_command_fallback_matches_command_and_stderr() {
+        let rules = default_command_fallbacks();
+        let conflict = ExecError::NonZero {
+            command: "codex review --base 'main' 'focus on tests'".to_string(),
+            result: CommandResult {
+                exit_code: 2,
+                stdout: String::new(),
+                stderr: "error: the argument '--base <BRANCH>' cannot be used with '[PROMPT]'"
+                    .to_string(),
+            },
+        };
+        assert_eq!(find_command_fallback(&conflict, &rules), Some(&rules[0]));
+
+        assert!(
+            find_command_fallback(&exit_with(2, "cannot be used with '[PROMPT]'"), &rules)
+                .is_none()
+        );
+        assert!(find_command_fallback(&non_zero("HTTP 502"), &rules).is_none());
+        assert!(find_command_fallback(&ExecError::Io("broken pipe".to_string()), &rules).is_none());
+
+        let any_command = [CommandFallback {
+            match_stderr: "unknown flag".to_string(),
+            match_command_substring: String::new(),
+            replacement_template: "codex exec fix".to_string(),
+        }];
+        assert!(
+            find_command_fallback(&exit_with(2, "error: unknown flag --x"), &any_command).is_some()
+        );
+    }
+
+    #[test]
+    fn should_retry_only_listed_exit_codes() {
+        let codes = [75, 124];
+        assert!(should_retry(&exit_with(75, "stream disconnected"), &codes));
+        assert!(should_retry(&exit_with(124, ""), &codes));
+        assert!(!should_retry(&exit_with(1, "stream disconnected"), &codes));
+        assert!(!should_retry(&exit_with(2, "usage error"), &codes));
+        assert!(should_retry(
+            &ExecError::Io("broken pipe".to_string()),
+            &codes
+        ));
+    }
+
+    #[test]
+    fn should_retry_without_list_keeps_marker_rules() {
+        assert!(should_retry(&exit_with(1, "HTTP 502: Bad Gateway"), &[]));
+        assert!(!should_retry(
+            &exit_with(1, "HTTP 401: Bad credentials"),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn secondary_rate_limit_wait_reads_retry_after_hint() {
+        let wait = |stderr: &str| secondary_rate_limit_wait(&non_zero(stderr));
+        assert_eq!(
+            wait(
+                "gh: You have exceeded a secondary rate limit. Please retry after 60 seconds. (HTTP 403)"
+            ),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            wait("HTTP 403: secondary rate limit exceeded\nRetry-After: 7\n"),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            wait("You have exceeded a secondary rate limit, retry after 2 minutes"),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            wait("secondary rate limit: retry after 0s"),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            wait("secondary rate limit; retry after 99999999999999999999999 seconds"),
+            Some(Duration::from_secs(MAX_RETRY_AFTER_SECONDS))
+        );
+        assert_eq!(
+            wait("You have exceeded a secondary rate limit. Please wait a few minutes."),
+            None
+        );
+        assert_eq!(wait("HTTP 502: Bad Gateway, retry after 5 seconds"), None);
+        assert_eq!(
+            secondary_rate_limit_wait(&ExecError::Io("broken pipe".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn is_retryable_keeps_transient_failures() {
+        assert!(is_retryable(&non_zero(
+            "fatal: unable to access 'https://github.com/org/repo/': Could not resolve host: github.com"
+        )));
+        assert!(is_retryable(&non_zero(
+            "error connecting to api.github.com: i/o timeout"
+        )));
+        assert!(is_retryable(&non_zero("HTTP 502: Bad Gateway")));
+        assert!(is_retryable(&ExecError::Io("broken pipe".to_string())));
+    }
+
+    #[test]
+    fn is_retryable_keeps_rate_limits() {
+        assert!(is_retryable(&non_zero(
+            "HTTP 403: API rate limit exceeded for user ID 1234."
+        )));
+        assert!(is_retryable(&non_zero(
+            "HTTP 403: You have exceeded a secondary rate limit."
+        )));
+        assert!(!is_retryable(&non_zero(
+            "HTTP 403: Resource not accessible by integration"
+        )));
+    }
+
+    #[test]
+    fn is_retryable_ignores_markers_in_stdout() {
+        let err = ExecError::NonZero {
+            command: "cargo test".to_string(),
+            result: CommandResult {
+                exit_code: 101,
+                stdout: "test lookup ... FAILED: file not found\nHTTP 404 from mock server"
+                    .to_string(),
+                stderr: "error: test failed, to rerun pass `--lib`".to_string(),
+            },
+        };
+        assert!(is_retryable(&err));
+    }
+
+    #[test]
+    fn parse_usage_reads_default_codex_summary() {
+        let pattern = Regex::new(&crate::models::default_usage_pattern()).unwrap();
+        let stderr = "OpenAI Codex v0.98.0\n[2026-01-02T10:00:00] tokens used: 12,345\n";
+        assert_eq!(
+            parse_usage(stderr, &pattern),
+            UsageSummary {
+                tokens_used: Some(12_345),
+                estimated_cost: None,
+            }
+        );
+
+        let with_cost =
+            "Token usage: total=1500 input=1000 output=500\ntokens used: 1500 (est. cost $0.42)\n";
+        let usage = parse_usage(with_cost, &pattern);
+        assert_eq!(usage.tokens_used, Some(1500));
+        assert_eq!(usage.estimated_cost, Some(0.42));
+    }
+
+    #[test]
+    fn parse_usage_handles_custom_and_non_matching_patterns() {
+        let stderr =
+            "usage: in=100 out=50 total=150 cost=0.01\nusage: in=10 out=5 total=15 cost=0.002\n";
+        let custom = Regex::new(r"total=(?P<tokens>\d+) cost=(?P<cost>[\d.]+)").unwrap();
+        let usage = parse_usage(stderr, &custom);
+        assert_eq!(usage.tokens_used, Some(165));
+        assert!((usage.estimated_cost.unwrap() - 0.012).abs() < 1e-9);
+
+        let tokens = Regex::new(r"tokens used: (?P<tokens>\d+)").unwrap();
+        assert_eq!(parse_usage("no summary", &tokens), UsageSummary::default());
+    }
+
+    #[test]
+    fn parse_session_id_reads_named_group_or_whole_match() {
+        let stderr = "OpenAI Codex v0.98.0\nsession id: 0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b\n";
+        assert_eq!(
+            parse_session_id(stderr, r"session id: (?P<session>[0-9a-f-]+)").as_deref(),
+            Some("0199a1b2-c3d4-7e5f-8a9b-0c1d2e3f4a5b")
+        );
+        assert_eq!(
+            parse_session_id("resume with sess_42 later", r"sess_\d+").as_deref(),
+            Some("sess_42")
+        );
+        assert_eq!(parse_session_id(stderr, r"thread: (?P<session>\S+)"), None);
+        assert_eq!(parse_session_id(stderr, "(unclosed"), None);
+    }
+
+    #[test]
+    fn review_is_clean_requires_marker_without_findings() {
+        let clean = "OpenAI Codex\nReviewed 4 files.\nNo issues found.\n";
+        assert!(review_is_clean(clean, "no issues found"));
+        assert!(!review_is_clean(clean, ""));
+
+        let mixed = "src/a.rs: no issues found\n- [P2] Clear stale metric columns — /tmp/b.ts:2\n";
+        assert!(!review_is_clean(mixed, "no issues found"));
+
+        let findings = "- [P1] Detect POSTPAY from nested GCP payment schedule — /tmp/a.ts:1";
+        assert!(!review_is_clean(findings, "no issues found"));
+    }
 
     #[test]
     fn infer_issue_level_prefers_highest_priority_p_level() {
@@ -848,6 +2130,246 @@ mod tests {
         );
     }
 
+    #[test]
+    fn idle_timeout_kills_silent_command() {
+        let started = std::time::Instant::now();
+        let err = run_shell_internal(
+            "echo started; sleep 5; echo never",
+            None,
+            true,
+            false,
+            None,
+            false,
+            OutputLimits {
+                idle_timeout: Some(Duration::from_millis(500)),
+                ..OutputLimits::default()
+            },
+        )
+        .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(matches!(err, ExecError::IdleTimeout { .. }));
+        assert!(!is_retryable(&err));
+    }
+
+    #[test]
+    fn idle_timeout_kills_processes_the_shell_started() {
+        let dir = std::env::temp_dir().join(format!(
+            "pr-reviewer-idle-group-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pid_file = dir.join("sleep.pid");
+        // The background sleep inherits stdout/stderr and would keep them open.
+        let command = format!(
+            "sleep 60 & echo $! > {}; echo started; wait",
+            super::sh_quote(&pid_file.display().to_string())
+        );
+        let started = std::time::Instant::now();
+        let err = run_shell_internal(
+            &command,
+            None,
+            true,
+            false,
+            None,
+            false,
+            OutputLimits {
+                idle_timeout: Some(Duration::from_secs(3)),
+                ..OutputLimits::default()
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ExecError::IdleTimeout { .. }));
+        assert!(started.elapsed() < Duration::from_secs(30));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        // A killed process whose parent is gone may linger as a zombie.
+        let state = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        let alive = !state.trim().is_empty() && !state.trim().starts_with('Z');
+        assert!(!alive, "background sleep {} survived", pid.trim());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn idle_timeout_allows_command_that_keeps_printing() {
+        let result = run_shell_internal(
+            "for i in 1 2 3; do echo tick $i; sleep 0.2; done",
+            None,
+            true,
+            false,
+            None,
+            false,
+            // Generous: the idle clock also covers the login shell's startup.
+            OutputLimits {
+                idle_timeout: Some(Duration::from_secs(10)),
+                ..OutputLimits::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(result.exit_code, 0);
+        assert!(result.stdout.contains("tick 1\ntick 2\ntick 3\n"));
+    }
+
+    #[test]
+    fn capture_buffer_keeps_head_and_tail_within_limit() {
+        let mut buffer = CaptureBuffer::new(40);
+        for i in 0..100 {
+            buffer.push_line(&format!("line {i:03}"));
+        }
+        let output = buffer.finish();
+        let (head, rest) = output.split_once(TRUNCATION_MARKER).unwrap();
+        assert_eq!(head, "line 000\nline 001\n");
+        assert_eq!(rest, ", 864 bytes omitted]\nline 098\nline 099\n");
+
+        let mut unlimited = CaptureBuffer::new(0);
+        for i in 0..100 {
+            unlimited.push_line(&format!("line {i:03}"));
+        }
+        assert_eq!(unlimited.finish().len(), 900);
+    }
+
+    #[test]
+    fn run_shell_internal_caps_captured_output() {
+        let result = run_shell_internal(
+            "seq 1 200000",
+            None,
+            true,
+            false,
+            None,
+            false,
+            OutputLimits {
+                max_captured_bytes: 4096,
+                ..OutputLimits::default()
+            },
+        )
+        .unwrap();
+        assert!(result.stdout.len() < 4096 + 100);
+        assert!(result.stdout.starts_with("1\n2\n3\n"));
+        assert!(result.stdout.contains(TRUNCATION_MARKER));
+        assert!(result.stdout.ends_with("199999\n200000\n"));
+    }
+
+    #[test]
+    fn command_wrapper_nests_quoted_command() {
+        let wrapper = CommandWrapper {
+            template: "docker run --rm -w /repo image sh -c {COMMAND}".to_string(),
+            scope: CommandWrapperScope::Codex,
+        };
+        let inner = r#"codex exec "don't stop" | tee 'out.log'"#;
+        assert_eq!(
+            wrapper.wrap(inner),
+            r#"docker run --rm -w /repo image sh -c 'codex exec "don'\''t stop" | tee '\''out.log'\'''"#
+        );
+        assert!(wrapper.applies_to("CODEX_HOME=/tmp/c codex review --base main"));
+        assert!(!wrapper.applies_to("git push origin HEAD"));
+        let all = CommandWrapper {
+            scope: CommandWrapperScope::All,
+            ..wrapper
+        };
+        assert!(all.applies_to("git push origin HEAD"));
+
+        let nested = CommandWrapper {
+            template: "sh -c {COMMAND}".to_string(),
+            scope: CommandWrapperScope::All,
+        }
+        .wrap(r#"printf '%s|' "it's" 'a b'"#);
+        let result = run_shell(&nested, None, true).unwrap();
+        assert_eq!(result.stdout, "it's|a b|");
+    }
+
+    #[test]
+    fn push_command_targets_configured_remote() {
+        assert_eq!(push_command(None, "feature/x"), "git push");
+        assert_eq!(
+            push_command(Some("upstream"), "feature/x"),
+            "git push upstream 'HEAD:feature/x'"
+        );
+    }
+
+    #[test]
+    fn github_token_is_exported_and_redacted() {
+        let token = "ghp_settingsTokenForTest";
+        set_github_token(Some(token.to_string()));
+        let exported = run_shell("printf %s \"$GH_TOKEN\"", None, true);
+        let failed = run_shell(
+            &format!("echo 'bad credentials for {token}' >&2; exit 1"),
+            None,
+            true,
+        );
+        set_github_token(None);
+
+        assert_eq!(exported.unwrap().stdout, token);
+        let err = failed.unwrap_err();
+        set_github_token(Some(token.to_string()));
+        let rendered = render_exec_error(&err);
+        let displayed = err.to_string();
+        set_github_token(None);
+        assert!(!rendered.contains(token), "{rendered}");
+        assert!(rendered.contains("bad credentials for ***"), "{rendered}");
+        assert!(!displayed.contains(token), "{displayed}");
+    }
+
+    /// Child half of `run_shell_prints_nothing_to_stdout`.
+    #[test]
+    #[ignore = "spawned by run_shell_prints_nothing_to_stdout"]
+    fn run_shell_quiet_child() {
+        run_shell("git --version", None, true).unwrap();
+    }
+
+    #[test]
+    fn run_shell_prints_nothing_to_stdout() {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "shell::tests::run_shell_quiet_child",
+                "--exact",
+                "--ignored",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("1 passed"));
+        assert!(!stdout.contains("Calendar-month fixed PR count"));
+        assert!(!stdout.contains("git version"));
+    }
+
+    #[test]
+    fn strip_ansi_removes_escape_codes_and_keeps_text() {
+        let colored =
+            "\x1b[1;35m[review]\x1b[0m \x1b[31m- [P1]\x1b[39m Fix \x1b[4mbug\x1b[24m\x1b[K";
+        assert_eq!(strip_ansi(colored), "[review] - [P1] Fix bug");
+
+        let osc = "\x1b]0;codex\x07see \x1b]8;;https://x.test\x1b\\link\x1b]8;;\x1b\\ done";
+        assert_eq!(strip_ansi(osc), "see link done");
+
+        let plain = "tokens used: 1,234 — ok [brackets] stay";
+        assert_eq!(strip_ansi(plain), plain);
+    }
+
+    #[test]
+    fn report_output_text_unwraps_json_reports() {
+        let json = serde_json::json!({
+            "pr_number": 3,
+            "stdout": "Review comment:\n\n- [P1] Handle empty input — src/lib.rs:10-12\n",
+            "stderr": "tokens used: 10",
+        })
+        .to_string();
+        let text = report_output_text(json);
+        assert!(text.starts_with("Review comment:\n\n- [P1] Handle empty input"));
+        assert!(text.ends_with("tokens used: 10"));
+        assert_eq!(parse_review_findings(&text).len(), 1);
+
+        let markdown = "# PR #3 Report\n\n- [P2] Tidy".to_string();
+        assert_eq!(report_output_text(markdown.clone()), markdown);
+    }
+
     #[test]
     fn derive_context_uses_fallback_when_report_missing() {
         let (summary, level) = derive_commit_context_from_report(None);
@@ -911,4 +2433,36 @@ extra";
         let message = extract_codex_commit_message(output);
         assert!(message.is_none());
     }
+
+    #[test]
+    fn reader_slots_bound_concurrent_acquisitions() {
+        let slots: &'static ReaderSlots = Box::leak(Box::new(ReaderSlots::new(2)));
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..6)
+            .map(|_| {
+                let (active, peak) = (Arc::clone(&active), Arc::clone(&peak));
+                std::thread::spawn(move || {
+                    let _permit = slots.acquire(1);
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(slots.in_use(), 0);
+
+        // More than the limit at once still runs alone instead of waiting forever.
+        let permit = slots.acquire(3);
+        assert_eq!(slots.in_use(), 3);
+        drop(permit);
+        slots.set_limit(0);
+        let _all: Vec<_> = (0..10).map(|_| slots.acquire(2)).collect();
+        assert_eq!(slots.in_use(), 20);
+    }
 }
@@ -1,19 +1,36 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
 use crate::models::{
-    AppSettings, EngineState, RunSnapshot, default_fix_template, default_review_template,
+    AppSettings, CachedPrList, EngineState, OpenPr, RunSnapshot, default_fix_template,
+    default_review_template,
 };
 
+#[derive(Debug, Clone)]
 pub struct StorePaths {
     pub root: PathBuf,
     pub settings: PathBuf,
     pub state: PathBuf,
     pub snapshot: PathBuf,
+    pub snapshots: PathBuf,
+    pub last_pr_list: PathBuf,
     pub reports: PathBuf,
     pub logs: PathBuf,
+    /// Append-only JSON lines of `run` events, next to the logs.
+    pub events: PathBuf,
+    pub run_lock: PathBuf,
+    pub ignored_prs: PathBuf,
+    /// `--repo-path`: checkout used instead of `settings.repo_path`/`repos` for
+    /// this invocation; never written back to `settings.json`.
+    pub repo_path_override: Option<PathBuf>,
+    /// Whether `load_settings` may save its migrations back to `settings`;
+    /// off for a `--config` file unless `--config-writeback` is passed.
+    pub settings_writeback: bool,
 }
 
 impl StorePaths {
@@ -24,21 +41,149 @@ impl StorePaths {
             let home = dirs::home_dir().context("cannot resolve home directory")?;
             home.join(".pr-reviewer-cli")
         };
+        Self::with_root(root)
+    }
 
+    /// Lays out the workspace under `root`, ignoring `PR_REVIEWER_HOME`.
+    pub fn with_root(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
         let paths = Self {
             settings: root.join("settings.json"),
             state: root.join("engine-state.json"),
             snapshot: root.join("run-snapshot.json"),
+            snapshots: root.join("snapshots"),
+            last_pr_list: root.join("last-pr-list.json"),
             reports: root.join("reports"),
             logs: root.join("logs"),
+            events: root.join("logs").join("events.jsonl"),
+            run_lock: root.join("run.lock"),
+            ignored_prs: root.join("ignored-prs.txt"),
+            repo_path_override: None,
+            settings_writeback: true,
             root,
         };
 
-        fs::create_dir_all(&paths.root)?;
+        fs::create_dir_all(&paths.root)
+            .with_context(|| format!("failed to create workspace: {}", paths.root.display()))?;
+        fs::create_dir_all(&paths.snapshots)?;
         fs::create_dir_all(&paths.reports)?;
         fs::create_dir_all(&paths.logs)?;
         Ok(paths)
     }
+
+    /// `--config PATH`: loads settings from `path` instead of the workspace's
+    /// `settings.json`. Without `writeback` nothing may be written there, so
+    /// the file has to exist already.
+    pub fn use_settings_file(&mut self, path: &Path, writeback: bool) -> Result<()> {
+        let settings =
+            std::path::absolute(path).map_err(|e| anyhow!("--config {}: {e}", path.display()))?;
+        if !writeback && !settings.is_file() {
+            bail!("--config {}: file not found", path.display());
+        }
+        self.settings = settings;
+        self.settings_writeback = writeback;
+        Ok(())
+    }
+}
+
+/// Returns the directory reports are written to: `settings.reports_dir` when set
+/// (relative paths resolve against the workspace root), otherwise `paths.reports`.
+pub fn resolve_reports_dir(paths: &StorePaths, settings: &AppSettings) -> Result<PathBuf> {
+    let Some(custom) = settings
+        .reports_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    else {
+        return Ok(paths.reports.clone());
+    };
+
+    let custom = PathBuf::from(custom);
+    let dir = if custom.is_absolute() {
+        custom
+    } else {
+        paths.root.join(custom)
+    };
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create reports directory: {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// PID lockfile held for the duration of a run so overlapping invocations
+/// (e.g. cron firing before the previous run finished) do not share the repo
+/// and snapshot. The file is `flock`ed while held, so the kernel frees the lock
+/// of a crashed process and two runs cannot both take over a stale one.
+/// Removed on drop.
+#[derive(Debug)]
+pub struct RunLock {
+    path: PathBuf,
+    _file: fs::File,
+}
+
+impl RunLock {
+    /// Takes `paths.run_lock`, replacing a lock whose process is gone.
+    pub fn acquire(paths: &StorePaths) -> Result<Self> {
+        let path = paths.run_lock.clone();
+        let context = || format!("failed to create lock: {}", path.display());
+        for _ in 0..3 {
+            let mut file = fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .with_context(context)?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(fs::TryLockError::WouldBlock) => {
+                    let holder = fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|content| content.trim().parse::<u32>().ok());
+                    match holder {
+                        Some(pid) => bail!(
+                            "another run in progress (pid {pid}, lock {})",
+                            path.display()
+                        ),
+                        None => bail!("another run in progress (lock {})", path.display()),
+                    }
+                }
+                Err(fs::TryLockError::Error(err)) => return Err(err).with_context(context),
+            }
+            // The previous holder may have removed the file between our open
+            // and lock; then lock whatever is at `path` now.
+            if !is_same_file(&file, &path) {
+                continue;
+            }
+            file.set_len(0)
+                .and_then(|()| write!(file, "{}", std::process::id()))
+                .with_context(|| format!("failed to write lock: {}", path.display()))?;
+            return Ok(Self { path, _file: file });
+        }
+        bail!("another run in progress (lock {})", path.display())
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Removes the run lock if this process holds it; for exits that skip `Drop`.
+pub fn release_own_run_lock(paths: &StorePaths) {
+    let own = fs::read_to_string(&paths.run_lock)
+        .is_ok_and(|content| content.trim() == std::process::id().to_string());
+    if own {
+        let _ = fs::remove_file(&paths.run_lock);
+    }
+}
+
+/// Whether the open `file` is still the one linked at `path`.
+fn is_same_file(file: &fs::File, path: &Path) -> bool {
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(linked)) => open.dev() == linked.dev() && open.ino() == linked.ino(),
+        _ => false,
+    }
 }
 
 pub fn load_json_or_default<T: for<'de> Deserialize<'de> + Default>(path: &Path) -> Result<T> {
@@ -54,19 +199,67 @@ pub fn load_json_or_default<T: for<'de> Deserialize<'de> + Default>(path: &Path)
 
 pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
     let content = serde_json::to_string_pretty(value)?;
-    fs::write(path, content)
-        .with_context(|| format!("failed to write file: {}", path.display()))?;
-    Ok(())
+    write_atomic(path, content)
+}
+
+/// Appends `value` to `path` as one JSON line. The line goes out in a single
+/// append-mode write, so concurrent writers never interleave inside a line.
+pub fn append_json_line<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open file: {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("failed to append to file: {}", path.display()))
+}
+
+/// Writes `content` to a hidden temp file next to `path` and renames it into
+/// place, so readers see the old or the new file but never a partial one.
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+    let temp = write_temp_sibling(path, content.as_ref())?;
+    fs::rename(&temp, path).with_context(|| {
+        let _ = fs::remove_file(&temp);
+        format!("failed to write file: {}", path.display())
+    })
+}
+
+/// First half of `write_atomic`: the flushed temp file, not yet renamed.
+fn write_temp_sibling(path: &Path, content: &[u8]) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("not a file path: {}", path.display()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()
+    };
+    write().with_context(|| format!("failed to write file: {}", temp.display()))?;
+    Ok(temp)
 }
 
 pub fn load_settings(paths: &StorePaths) -> Result<AppSettings> {
     if !paths.settings.exists() {
+        if !paths.settings_writeback {
+            bail!("settings file not found: {}", paths.settings.display());
+        }
         let defaults = AppSettings::default();
         save_json(&paths.settings, &defaults)?;
         return Ok(defaults);
     }
 
-    let mut settings: AppSettings = load_json_or_default(&paths.settings)?;
+    let content = fs::read_to_string(&paths.settings)
+        .with_context(|| format!("failed to read file: {}", paths.settings.display()))?;
+    let mut settings: AppSettings = serde_json::from_str(&content).map_err(|err| {
+        anyhow!(
+            "settings parse error in {}: {err}",
+            paths.settings.display()
+        )
+    })?;
     let mut migrated = false;
 
     if settings
@@ -94,7 +287,7 @@ pub fn load_settings(paths: &StorePaths) -> Result<AppSettings> {
         migrated = true;
     }
 
-    if migrated {
+    if migrated && paths.settings_writeback {
         save_json(&paths.settings, &settings)?;
     }
 
@@ -114,5 +307,368 @@ pub fn load_snapshot(paths: &StorePaths) -> Result<RunSnapshot> {
 }
 
 pub fn save_snapshot(paths: &StorePaths, snapshot: &RunSnapshot) -> Result<()> {
-    save_json(&paths.snapshot, snapshot)
+    save_json(&paths.snapshot, snapshot)?;
+    if snapshot.finished_at.is_some() {
+        let settings = load_settings(paths)?;
+        archive_snapshot(paths, snapshot, settings.max_snapshot_history)?;
+    }
+    Ok(())
+}
+
+pub fn save_last_pr_list(paths: &StorePaths, repo: Option<&str>, prs: &[OpenPr]) -> Result<()> {
+    save_json(
+        &paths.last_pr_list,
+        &CachedPrList {
+            saved_at: Some(Utc::now()),
+            repo: repo.map(str::to_string),
+            prs: prs.to_vec(),
+        },
+    )
+}
+
+pub fn load_last_pr_list(paths: &StorePaths) -> Result<CachedPrList> {
+    load_json_or_default(&paths.last_pr_list)
+}
+
+/// Archive id of a snapshot, derived from its start time (`-` instead of `:` so it is file-safe).
+pub fn snapshot_archive_id(snapshot: &RunSnapshot) -> Option<String> {
+    snapshot
+        .started_at
+        .map(|started| started.to_rfc3339().replace(':', "-"))
+}
+
+/// Writes a finished snapshot to `snapshots/run-<id>.json` and keeps only the newest
+/// `max_history` archives. A `max_history` of 0 disables archiving.
+pub fn archive_snapshot(
+    paths: &StorePaths,
+    snapshot: &RunSnapshot,
+    max_history: usize,
+) -> Result<()> {
+    if max_history == 0 {
+        return Ok(());
+    }
+    let Some(id) = snapshot_archive_id(snapshot) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&paths.snapshots)?;
+    save_json(&paths.snapshots.join(format!("run-{id}.json")), snapshot)?;
+
+    let ids = list_archived_snapshot_ids(paths)?;
+    if ids.len() > max_history {
+        for stale in &ids[max_history..] {
+            let _ = fs::remove_file(paths.snapshots.join(format!("run-{stale}.json")));
+        }
+    }
+    Ok(())
+}
+
+/// Archived snapshot ids, newest first.
+pub fn list_archived_snapshot_ids(paths: &StorePaths) -> Result<Vec<String>> {
+    if !paths.snapshots.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(&paths.snapshots)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(id) = name
+            .strip_prefix("run-")
+            .and_then(|rest| rest.strip_suffix(".json"))
+        {
+            ids.push(id.to_string());
+        }
+    }
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(ids)
+}
+
+pub fn load_archived_snapshot(paths: &StorePaths, id: &str) -> Result<RunSnapshot> {
+    let id = id.trim_start_matches("run-").trim_end_matches(".json");
+    let path = paths.snapshots.join(format!("run-{id}.json"));
+    if !path.exists() {
+        bail!("archived run not found: {id}");
+    }
+    load_json_or_default(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RunLock, StorePaths, archive_snapshot, list_archived_snapshot_ids, load_archived_snapshot,
+        load_last_pr_list, load_settings, load_snapshot, resolve_reports_dir, save_json,
+        save_last_pr_list, write_temp_sibling,
+    };
+    use crate::models::{AppSettings, OpenPr, RunSnapshot, RunStatus};
+    use chrono::{TimeZone, Utc};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_paths(name: &str) -> StorePaths {
+        let root = std::env::temp_dir().join(format!(
+            "pr-reviewer-store-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        StorePaths::with_root(root).unwrap()
+    }
+
+    #[test]
+    fn save_json_keeps_previous_file_when_interrupted_before_rename() {
+        let paths = temp_paths("atomic-write");
+        let running = RunSnapshot {
+            status: RunStatus::Running,
+            current_index: 3,
+            ..RunSnapshot::default()
+        };
+        save_json(&paths.snapshot, &running).unwrap();
+
+        // A crash after writing the temp file but before the rename.
+        let temp = write_temp_sibling(&paths.snapshot, b"{ \"status\": \"succ").unwrap();
+        assert_ne!(temp, paths.snapshot);
+        assert_eq!(temp.parent(), paths.snapshot.parent());
+        let loaded = load_snapshot(&paths).unwrap();
+        assert_eq!(loaded.status, RunStatus::Running);
+        assert_eq!(loaded.current_index, 3);
+
+        let finished = RunSnapshot {
+            status: RunStatus::Succeeded,
+            ..running
+        };
+        save_json(&paths.snapshot, &finished).unwrap();
+        assert_eq!(load_snapshot(&paths).unwrap().status, RunStatus::Succeeded);
+        assert!(!temp.exists());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn external_settings_file_is_read_without_writing_back() {
+        let paths = temp_paths("external-config");
+        let config = paths.root.join("versioned").join("pr-reviewer.json");
+        fs::create_dir_all(config.parent().unwrap()).unwrap();
+        let content = r#"{"max_prs_per_run": 3, "fix_command_template": "codex fix"}"#;
+        fs::write(&config, content).unwrap();
+        let external = StorePaths {
+            settings: config.clone(),
+            settings_writeback: false,
+            ..paths.clone()
+        };
+
+        let settings = load_settings(&external).unwrap();
+        assert_eq!(settings.max_prs_per_run, 3);
+        assert_eq!(
+            settings.fix_command_template,
+            AppSettings::default().fix_command_template
+        );
+        assert_eq!(fs::read_to_string(&config).unwrap(), content);
+        assert!(!paths.settings.exists());
+
+        let writeback = StorePaths {
+            settings_writeback: true,
+            ..external
+        };
+        load_settings(&writeback).unwrap();
+        assert_ne!(fs::read_to_string(&config).unwrap(), content);
+        assert!(!paths.settings.exists());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn missing_config_file_is_only_created_with_writeback() {
+        let mut paths = temp_paths("missing-config");
+        let config = paths.root.join("missing.json");
+
+        let err = paths.clone().use_settings_file(&config, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("--config {}: file not found", config.display())
+        );
+        let external = StorePaths {
+            settings: config.clone(),
+            settings_writeback: false,
+            ..paths.clone()
+        };
+        assert!(load_settings(&external).is_err());
+        assert!(!config.exists());
+
+        paths.use_settings_file(&config, true).unwrap();
+        load_settings(&paths).unwrap();
+        assert!(config.is_file());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn with_root_lays_out_workspace_under_given_directory() {
+        let paths = temp_paths("with-root");
+        let root = paths.root.clone();
+        assert_eq!(paths.settings, root.join("settings.json"));
+        assert_eq!(paths.state, root.join("engine-state.json"));
+        assert_eq!(paths.snapshot, root.join("run-snapshot.json"));
+        assert_eq!(paths.last_pr_list, root.join("last-pr-list.json"));
+        assert_eq!(paths.reports, root.join("reports"));
+        assert!(paths.snapshots.is_dir());
+        assert!(paths.reports.is_dir());
+        assert!(paths.logs.is_dir());
+        assert_eq!(paths.run_lock, root.join("run.lock"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn run_lock_blocks_second_run_until_released() {
+        let paths = temp_paths("run-lock");
+        let lock = RunLock::acquire(&paths).unwrap();
+        assert_eq!(
+            fs::read_to_string(&paths.run_lock).unwrap(),
+            std::process::id().to_string()
+        );
+        let err = RunLock::acquire(&paths).unwrap_err();
+        assert!(err.to_string().starts_with("another run in progress"));
+
+        drop(lock);
+        assert!(!paths.run_lock.exists());
+        drop(RunLock::acquire(&paths).unwrap());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn run_lock_replaces_stale_lock() {
+        let paths = temp_paths("run-lock-stale");
+        let mut exited = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = exited.id();
+        exited.wait().unwrap();
+        fs::write(&paths.run_lock, dead_pid.to_string()).unwrap();
+        let lock = RunLock::acquire(&paths).unwrap();
+        assert_eq!(
+            fs::read_to_string(&paths.run_lock).unwrap(),
+            std::process::id().to_string()
+        );
+        drop(lock);
+
+        fs::write(&paths.run_lock, "garbage").unwrap();
+        drop(RunLock::acquire(&paths).unwrap());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn run_lock_stale_takeover_has_one_winner() {
+        let paths = temp_paths("run-lock-race");
+        let mut exited = std::process::Command::new("true").spawn().unwrap();
+        let dead_pid = exited.id();
+        exited.wait().unwrap();
+        fs::write(&paths.run_lock, dead_pid.to_string()).unwrap();
+
+        let start = std::sync::Barrier::new(8);
+        let results: Vec<anyhow::Result<RunLock>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        start.wait();
+                        RunLock::acquire(&paths)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert_eq!(
+            fs::read_to_string(&paths.run_lock).unwrap(),
+            std::process::id().to_string()
+        );
+        drop(results);
+        assert!(!paths.run_lock.exists());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn resolve_reports_dir_defaults_to_workspace_reports() {
+        let paths = temp_paths("reports-default");
+        let settings = AppSettings::default();
+        assert_eq!(
+            resolve_reports_dir(&paths, &settings).unwrap(),
+            paths.reports
+        );
+    }
+
+    #[test]
+    fn resolve_reports_dir_uses_override_and_creates_it() {
+        let paths = temp_paths("reports-override");
+        let custom: PathBuf = paths.root.join("shared").join("reports");
+        let settings = AppSettings {
+            reports_dir: Some(custom.display().to_string()),
+            ..AppSettings::default()
+        };
+        let resolved = resolve_reports_dir(&paths, &settings).unwrap();
+        assert_eq!(resolved, custom);
+        assert!(resolved.is_dir());
+
+        let relative = AppSettings {
+            reports_dir: Some("out".to_string()),
+            ..AppSettings::default()
+        };
+        assert_eq!(
+            resolve_reports_dir(&paths, &relative).unwrap(),
+            paths.root.join("out")
+        );
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    fn finished_snapshot(hour: u32) -> RunSnapshot {
+        RunSnapshot {
+            started_at: Some(Utc.with_ymd_and_hms(2026, 1, 2, hour, 0, 0).unwrap()),
+            finished_at: Some(Utc.with_ymd_and_hms(2026, 1, 2, hour, 5, 0).unwrap()),
+            status: RunStatus::Succeeded,
+            total_prs: hour as usize,
+            ..RunSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn archive_snapshot_keeps_newest_runs_only() {
+        let paths = temp_paths("snapshot-archive");
+        for hour in 1..=4 {
+            archive_snapshot(&paths, &finished_snapshot(hour), 2).unwrap();
+        }
+        let ids = list_archived_snapshot_ids(&paths).unwrap();
+        assert_eq!(
+            ids,
+            vec![
+                "2026-01-02T04-00-00+00-00".to_string(),
+                "2026-01-02T03-00-00+00-00".to_string()
+            ]
+        );
+
+        let loaded = load_archived_snapshot(&paths, &ids[1]).unwrap();
+        assert_eq!(loaded.total_prs, 3);
+        assert!(load_archived_snapshot(&paths, "missing").is_err());
+        let _ = fs::remove_dir_all(&paths.root);
+    }
+
+    #[test]
+    fn last_pr_list_round_trips() {
+        let paths = temp_paths("last-pr-list");
+        assert!(load_last_pr_list(&paths).unwrap().prs.is_empty());
+
+        let prs = vec![
+            OpenPr {
+                number: 7,
+                title: "Fix login".to_string(),
+                ..OpenPr::default()
+            },
+            OpenPr {
+                number: 3,
+                ..OpenPr::default()
+            },
+        ];
+        save_last_pr_list(&paths, None, &prs).unwrap();
+        let cached = load_last_pr_list(&paths).unwrap();
+        assert!(cached.saved_at.is_some());
+        assert_eq!(
+            cached.prs.iter().map(|pr| pr.number).collect::<Vec<_>>(),
+            vec![7, 3]
+        );
+        assert_eq!(cached.prs[0].title, "Fix login");
+        let _ = fs::remove_dir_all(&paths.root);
+    }
 }